@@ -0,0 +1,26 @@
+#![no_main]
+
+use std::thread::sleep;
+use std::time::Duration;
+
+use esp_idf_hal::eth::*;
+
+#[no_mangle]
+fn app_main() {
+  let config = EthConfig::builder()
+    .phy(Phy::Lan8720)
+    .phy_addr(0)
+    .build();
+
+  let eth = Eth::start(config).expect("failed to start Ethernet");
+
+  loop {
+    if eth.is_link_up() {
+      println!("Link up, IP: {:?}", eth.ip_info());
+    } else {
+      println!("Link down.");
+    }
+
+    sleep(Duration::from_secs(5));
+  }
+}
@@ -3,6 +3,7 @@
 use std::time::Duration;
 
 use futures::executor::block_on;
+use futures::stream::StreamExt;
 
 use esp_idf_hal::{nvs::*, wifi::*};
 
@@ -14,12 +15,15 @@ fn app_main() {
     let scan_config = ScanConfig::builder()
       .show_hidden(true)
       .scan_type(ScanType::Passive { max: Duration::from_secs(1) })
-      .build();
+      .build()
+      .unwrap();
 
-    loop {
-      println!("Scanning...");
+    let mut scans = wifi.scan_stream(&scan_config, Duration::from_secs(5)).unwrap();
+
+    println!("Scanning...");
 
-      match wifi.scan(&scan_config).await {
+    while let Some(scan) = scans.next().await {
+      match scan {
         Ok(aps) => {
           if aps.is_empty() {
             println!("No access points found.");
@@ -27,7 +31,7 @@ fn app_main() {
             println!("Found {} access points:", aps.len());
 
             for ap in aps {
-              println!("  - {} '{}'", ap.bssid(), ap.ssid())
+              println!("  - {} '{}' (channel {}, {} dBm, {:?})", ap.bssid(), ap.ssid(), ap.channel(), ap.rssi(), ap.auth_mode())
             }
           }
         }
@@ -35,6 +39,8 @@ fn app_main() {
           eprintln!("WiFi Scan failed: {}", err);
         }
       }
+
+      println!("Scanning...");
     }
   })
 }
@@ -5,6 +5,8 @@ use std::thread;
 
 use esp_idf_hal::interface::Interface;
 
+use crate::ap_socket;
+
 use dnsparse::{Header, HeaderKind, Answer, QueryKind, QueryClass, Message, OpCode, ResponseCode};
 
 pub fn handle_request(socket: &UdpSocket, src: SocketAddr, request: Message, ip: &Ipv4Addr) -> io::Result<usize> {
@@ -53,22 +55,38 @@ pub fn handle_request(socket: &UdpSocket, src: SocketAddr, request: Message, ip:
 pub fn server() {
   println!("Starting DNS server …");
 
-  let socket = UdpSocket::bind("0.0.0.0:53").unwrap();
-  socket.set_read_timeout(Some(Duration::from_secs(30))).unwrap();
-  socket.set_write_timeout(Some(Duration::from_secs(30))).unwrap();
-
-  let ip = *Interface::Ap.ip_info().ip();
-  println!("IP: {:?}", ip);
+  let mut bound_socket = None;
 
   loop {
     thread::yield_now();
 
+    let socket = ap_socket(&mut bound_socket, || {
+      let socket = UdpSocket::bind("0.0.0.0:53")?;
+      // Time out regularly to re-bind when the access point is restarted.
+      socket.set_read_timeout(Some(Duration::from_secs(1)))?;
+      socket.set_write_timeout(Some(Duration::from_secs(30)))?;
+      Ok(socket)
+    });
+
+    let socket = match socket {
+      Ok(Some(socket)) => socket,
+      Ok(None) => {
+        thread::sleep(Duration::from_secs(1));
+        continue
+      },
+      Err(err) => {
+        eprintln!("Failed starting DNS server: {}", err);
+        thread::sleep(Duration::from_secs(1));
+        continue
+      },
+    };
+
     let mut buf = Message::BUFFER;
 
     let (len, src) = match socket.recv_from(&mut buf) {
       Ok(ok) => ok,
       Err(err) => {
-        if err.kind() != std::io::ErrorKind::WouldBlock {
+        if err.kind() != std::io::ErrorKind::WouldBlock && err.kind() != std::io::ErrorKind::TimedOut {
           eprintln!("Receiving DNS request failed: {}", err);
         }
         continue
@@ -82,7 +100,12 @@ pub fn server() {
       continue
     };
 
-    if let Err(err) = handle_request(&socket, src, request, &ip) {
+    let ip = match Interface::Ap.ip_info() {
+      Some(ip_info) => *ip_info.ip(),
+      None => continue,
+    };
+
+    if let Err(err) = handle_request(socket, src, request, &ip) {
       eprintln!("Error sending response to '{:?}': {}", src, err);
     }
   }
@@ -4,6 +4,7 @@
 use std::sync::{Arc, Mutex};
 use std::thread::{self, sleep};
 use std::time::Duration;
+use std::io;
 use std::net::{Ipv4Addr, SocketAddrV4};
 use std::net::TcpListener;
 
@@ -19,6 +20,34 @@ use wifi_manager::*;
 
 mod dns;
 
+/// Get a socket bound to the access point interface, creating it using `bind` if the access
+/// point's network interface was re-created since `socket` was bound, e.g. when it was restarted.
+///
+/// Returns `None` while the access point is not running.
+pub(crate) fn ap_socket<'s, S: BindToInterface>(
+  socket: &'s mut Option<(String, S)>,
+  bind: impl FnOnce() -> io::Result<S>,
+) -> io::Result<Option<&'s S>> {
+  let netif_name = match Interface::Ap.netif_name() {
+    Some(netif_name) => netif_name,
+    None => {
+      *socket = None;
+      return Ok(None)
+    },
+  };
+
+  if socket.as_ref().map_or(true, |(bound, _)| *bound != netif_name) {
+    // Close the previous socket first, so its address can be bound again.
+    *socket = None;
+
+    let new_socket = bind()?;
+    new_socket.bind_to_interface(Interface::Ap)?;
+    *socket = Some((netif_name, new_socket));
+  }
+
+  Ok(socket.as_ref().map(|(_, socket)| socket))
+}
+
 #[no_mangle]
 pub fn app_main() {
   block_on(async {
@@ -85,7 +114,8 @@ async fn rust_blink_and_write() -> Result<!, EspError> {
 
         let ap_config = ApConfig::builder()
           .ssid(ap_ssid)
-          .build();
+          .build()
+          .expect("invalid access point configuration");
 
         let wifi_storage = namespace;
 
@@ -101,15 +131,37 @@ async fn rust_blink_and_write() -> Result<!, EspError> {
           wifi_running = wifi.start_ap(ap_config).unwrap();
         }
 
-        let stream = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 80)).expect("failed starting TCP listener");
-
         let wifi_running = Arc::new(Mutex::new(Some(wifi_running)));
         let wifi_storage = Arc::new(Mutex::new(wifi_storage));
 
+        let mut listener = None;
+
         loop {
           thread::yield_now();
 
+          // Only serve the configuration page to clients of the access point,
+          // which may only be started later as a fallback.
+          let stream = ap_socket(&mut listener, || {
+            let listener = TcpListener::bind(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 80))?;
+            listener.set_nonblocking(true)?;
+            Ok(listener)
+          });
+
+          let stream = match stream {
+            Ok(Some(stream)) => stream,
+            Ok(None) => {
+              sleep(Duration::from_secs(1));
+              continue
+            },
+            Err(e) => {
+              eprintln!("Failed starting TCP listener: {}", e);
+              sleep(Duration::from_secs(1));
+              continue
+            },
+          };
+
           let client = stream.accept().and_then(|(client, addr)| {
+            client.set_nonblocking(false)?;
             client.set_read_timeout(Some(Duration::from_secs(30)))?;
             client.set_write_timeout(Some(Duration::from_secs(30)))?;
             Ok((client, addr))
@@ -127,7 +179,7 @@ async fn rust_blink_and_write() -> Result<!, EspError> {
                 }))
                 .unwrap();
             },
-            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => (),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => sleep(Duration::from_millis(100)),
             Err(e) => eprintln!("Client error: {}", e),
           }
         }
@@ -25,8 +25,11 @@ fn ssid_and_password(params: &[u8]) -> (Option<Ssid>, Option<Password>) {
 async fn handle_index(wifi: Arc<Mutex<Option<WifiRunning>>>, mut client: TcpStream) -> io::Result<()> {
   let scan_config = ScanConfig::builder()
     .show_hidden(true)
+    .home_channel_dwell(Duration::from_millis(50))
     .scan_type(ScanType::Passive { max: Duration::from_millis(100) })
-    .build();
+    .max_age(Duration::from_secs(10))
+    .build()
+    .expect("invalid scan configuration");
 
   writeln!(client, "HTTP/1.1 200 OK")?;
   writeln!(client, "Content-Type: text/html")?;
@@ -35,13 +38,16 @@ async fn handle_index(wifi: Arc<Mutex<Option<WifiRunning>>>, mut client: TcpStre
 
   writeln!(client, "<datalist id='ssids'>")?;
 
-  if let Some(wifi) = &mut *wifi.lock().unwrap() {
-    match wifi.scan(&scan_config).await {
-      Ok(mut aps) => {
-        aps.sort_by(|a, b| a.ssid().cmp(b.ssid()));
-        aps.dedup_by(|a, b| a.ssid() == b.ssid());
+  // Don't hold the lock while scanning, concurrent scans are shared.
+  let scan = wifi.lock().unwrap().as_mut().map(|wifi| wifi.scan(&scan_config));
+
+  if let Some(scan) = scan {
+    match scan.await {
+      Ok(aps) => {
+        let mut aps = aps.without_hidden().strongest_per_ssid();
+        aps.sort_by_ssid();
 
-        for ssid in aps.iter().map(|ap| ap.ssid()).filter(|ssid| !ssid.is_empty()) {
+        for ssid in aps.iter().map(|ap| ap.ssid()) {
           writeln!(client, "<option>{}</option>", ssid)?;
         }
 
@@ -54,6 +60,17 @@ async fn handle_index(wifi: Arc<Mutex<Option<WifiRunning>>>, mut client: TcpStre
 
   writeln!(client, "</datalist>")?;
 
+  let stations = match &*wifi.lock().unwrap() {
+    Some(WifiRunning::Ap(ap)) => ap.stations().ok(),
+    Some(WifiRunning::ApSta(ap_sta)) => ap_sta.stations().ok(),
+    _ => None,
+  };
+
+  if let Some(stations) = stations {
+    let s = if stations.len() == 1 { "" } else { "s" };
+    writeln!(client, "<p>{} device{} connected</p>", stations.len(), s)?;
+  }
+
   Ok(())
 }
 
@@ -126,9 +143,29 @@ pub async fn handle_request(
 
               let mut wifi_running = wifi_running.lock().unwrap();
 
-              if let Some(WifiRunning::Ap(ap)) = wifi_running.take() {
-                let (ap_config, wifi) = ap.stop();
-                wifi_running.replace(connect_ssid_password(wifi, ap_config, ssid, password).await);
+              match wifi_running.take() {
+                Some(WifiRunning::Ap(ap)) => {
+                  // Keep the access point running so the client stays connected.
+                  wifi_running.replace(connect_ssid_password_ap_sta(ap, ssid, password));
+                },
+                Some(WifiRunning::ApSta(ap_sta)) => {
+                  let (_, ap) = ap_sta.stop_sta();
+                  wifi_running.replace(connect_ssid_password_ap_sta(ap, ssid, password));
+                },
+                Some(WifiRunning::Sta(sta)) => {
+                  // Reconfiguring is only possible while the fallback access point is running,
+                  // keep it running so the client stays connected.
+                  match sta.into_ap_sta() {
+                    Ok(ap_sta) => {
+                      let (_, ap) = ap_sta.stop_sta();
+                      wifi_running.replace(connect_ssid_password_ap_sta(ap, ssid, password));
+                    },
+                    Err(sta) => {
+                      wifi_running.replace(WifiRunning::Sta(sta));
+                    },
+                  }
+                },
+                None => (),
               }
             }
           }
@@ -146,24 +183,58 @@ pub async fn handle_request(
   }
 }
 
-/// Try to connect to an access point with the given `ssid` and `password` in station mode, otherwise revert to access point mode.
-pub async fn connect_ssid_password(wifi: Wifi, ap_config: ApConfig, ssid: Ssid, password: Password) -> WifiRunning {
-  let sta_config = StaConfig::builder()
+fn sta_config(ssid: Ssid, password: Password) -> Result<StaConfig, StaConfigError> {
+  StaConfig::builder()
     .ssid(ssid)
     .password(password)
-    .build();
+    .build()
+}
+
+/// Connect to an access point with the given `ssid` and `password` while keeping the access point `ap` running.
+fn connect_ssid_password_ap_sta(ap: Wifi<ApConfig>, ssid: Ssid, password: Password) -> WifiRunning {
+  let sta_config = match sta_config(ssid, password) {
+    Ok(sta_config) => sta_config,
+    Err(err) => {
+      eprintln!("Invalid station configuration: {}", err);
+      return WifiRunning::Ap(ap)
+    },
+  };
 
   eprintln!("Connecting to '{}' with password '{}' …", sta_config.ssid(), sta_config.password());
 
-  match wifi.connect_sta(sta_config).await {
-    Ok(sta) => {
-      if let WifiRunning::Sta(ref sta) = sta {
-        eprintln!("Connected to '{}' with IP '{}'.", sta.config().ssid(), sta.ip_info().ip());
-      }
-      sta
+  match ap.start_sta(sta_config) {
+    Ok(wifi_running) => wifi_running,
+    Err((err, ap)) => {
+      eprintln!("Failed to start station: {}", err);
+      WifiRunning::Ap(ap)
     },
+  }
+}
+
+/// Connect to an access point with the given `ssid` and `password` in station mode, falling back to access point mode
+/// after repeated failures.
+pub async fn connect_ssid_password(wifi: Wifi, ap_config: ApConfig, ssid: Ssid, password: Password) -> WifiRunning {
+  let sta_config = match sta_config(ssid, password) {
+    Ok(sta_config) => sta_config,
     Err(err) => {
-      err.wifi().start_ap(ap_config).expect("Failed to start access point")
+      eprintln!("Invalid station configuration: {}", err);
+      return wifi.start_ap(ap_config).expect("Failed to start access point")
+    },
+  };
+
+  let supervisor_config = SupervisorConfig::builder()
+    .fallback_ap(3, ap_config)
+    .build();
+
+  eprintln!("Connecting to '{}' with password '{}' …", sta_config.ssid(), sta_config.password());
+
+  let wifi_running = wifi.connect_sta_supervised(sta_config, supervisor_config).await.expect("Failed to start access point");
+
+  if let WifiRunning::Sta(ref sta) = wifi_running {
+    if let Some(ip_info) = sta.ip_info() {
+      eprintln!("Connected to '{}' with IP '{}'.", sta.config().ssid(), ip_info.ip());
     }
   }
+
+  wifi_running
 }
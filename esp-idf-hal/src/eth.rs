@@ -0,0 +1,337 @@
+use core::fmt;
+use core::mem;
+use core::ptr;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering::SeqCst};
+
+use esp_idf_bindgen::{
+  esp_err_t,
+  esp_eth_config_t,
+  esp_eth_handle_t,
+  esp_eth_mac_t,
+  esp_eth_phy_t,
+  esp_eth_mac_new_esp32,
+  esp_eth_phy_new_dp83848,
+  esp_eth_phy_new_ip101,
+  esp_eth_phy_new_lan8720,
+  esp_eth_phy_new_rtl8201,
+  esp_eth_driver_install,
+  esp_eth_driver_uninstall,
+  esp_eth_new_netif_glue,
+  esp_eth_del_netif_glue,
+  esp_eth_set_default_handlers,
+  esp_eth_clear_default_handlers,
+  esp_eth_start,
+  esp_eth_stop,
+  esp_netif_attach,
+  esp_netif_config_t,
+  esp_netif_destroy,
+  esp_netif_new,
+  esp_netif_t,
+  eth_event_t,
+  eth_mac_config_t,
+  eth_phy_config_t,
+  _g_esp_netif_inherent_eth_config,
+  _g_esp_netif_netstack_default_eth,
+  ESP_ERR_INVALID_STATE,
+  ESP_ERR_NO_MEM,
+  ESP_EVENT_ANY_ID,
+  ETH_EVENT,
+};
+
+use crate::EspError;
+use crate::event::EventHandler;
+use crate::interface::{Interface, IpInfo};
+use crate::wifi::{initialize_network_interface, event_loop_create_default};
+
+static ETH_ACTIVE: AtomicBool = AtomicBool::new(false);
+pub(crate) static ETH_PTR: AtomicUsize = AtomicUsize::new(0);
+
+/// An Ethernet PHY chip.
+#[derive(Debug, Clone, Copy)]
+pub enum Phy {
+  Lan8720,
+  Ip101,
+  Rtl8201,
+  Dp83848,
+}
+
+impl Phy {
+  fn create(self, config: &eth_phy_config_t) -> *mut esp_eth_phy_t {
+    unsafe {
+      match self {
+        Self::Lan8720 => esp_eth_phy_new_lan8720(config),
+        Self::Ip101 => esp_eth_phy_new_ip101(config),
+        Self::Rtl8201 => esp_eth_phy_new_rtl8201(config),
+        Self::Dp83848 => esp_eth_phy_new_dp83848(config),
+      }
+    }
+  }
+}
+
+/// Configuration for the internal Ethernet MAC and an external PHY.
+///
+/// The RMII clock mode and clock pin are configured using `CONFIG_ETH_RMII_CLK_INPUT`,
+/// `CONFIG_ETH_RMII_CLK_OUTPUT` and `CONFIG_ETH_RMII_CLK_IN_GPIO`/`CONFIG_ETH_RMII_CLK_OUT_GPIO`
+/// in `sdkconfig`, since they cannot be changed at runtime.
+#[derive(Debug, Clone)]
+pub struct EthConfig {
+  phy: Phy,
+  phy_addr: i32,
+  phy_reset_pin: Option<u8>,
+  mdc_pin: u8,
+  mdio_pin: u8,
+}
+
+impl EthConfig {
+  pub fn phy(&self) -> Phy {
+    self.phy
+  }
+
+  pub fn builder() -> EthConfigBuilder {
+    EthConfigBuilder::default()
+  }
+}
+
+/// Builder for [`EthConfig`](struct.EthConfig.html).
+#[derive(Debug, Clone)]
+pub struct EthConfigBuilder {
+  phy: Phy,
+  phy_addr: i32,
+  phy_reset_pin: Option<u8>,
+  mdc_pin: u8,
+  mdio_pin: u8,
+}
+
+impl Default for EthConfigBuilder {
+  fn default() -> Self {
+    Self {
+      phy: Phy::Lan8720,
+      phy_addr: -1,
+      phy_reset_pin: None,
+      mdc_pin: 23,
+      mdio_pin: 18,
+    }
+  }
+}
+
+impl EthConfigBuilder {
+  pub fn phy(&mut self, phy: Phy) -> &mut Self {
+    self.phy = phy;
+    self
+  }
+
+  /// Set the SMI address of the PHY. By default, the address is detected automatically.
+  pub fn phy_addr(&mut self, phy_addr: u8) -> &mut Self {
+    self.phy_addr = phy_addr as i32;
+    self
+  }
+
+  pub fn phy_reset_pin(&mut self, phy_reset_pin: impl Into<Option<u8>>) -> &mut Self {
+    self.phy_reset_pin = phy_reset_pin.into();
+    self
+  }
+
+  /// Set the SMI MDC pin. Defaults to GPIO 23.
+  pub fn mdc_pin(&mut self, mdc_pin: u8) -> &mut Self {
+    self.mdc_pin = mdc_pin;
+    self
+  }
+
+  /// Set the SMI MDIO pin. Defaults to GPIO 18.
+  pub fn mdio_pin(&mut self, mdio_pin: u8) -> &mut Self {
+    self.mdio_pin = mdio_pin;
+    self
+  }
+
+  pub fn build(&self) -> EthConfig {
+    EthConfig {
+      phy: self.phy,
+      phy_addr: self.phy_addr,
+      phy_reset_pin: self.phy_reset_pin,
+      mdc_pin: self.mdc_pin,
+      mdio_pin: self.mdio_pin,
+    }
+  }
+}
+
+/// A running Ethernet interface.
+#[must_use = "Ethernet will be stopped immediately. Drop it explicitly after you are done using it or create a named binding."]
+pub struct Eth {
+  config: EthConfig,
+  mac: *mut esp_eth_mac_t,
+  phy: *mut esp_eth_phy_t,
+  handle: esp_eth_handle_t,
+  netif: *mut esp_netif_t,
+  glue: *mut libc::c_void,
+  link_up: Arc<AtomicBool>,
+  link_handler: Option<EventHandler>,
+}
+
+unsafe impl Send for Eth {}
+
+impl fmt::Debug for Eth {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Eth")
+      .field("config", &self.config)
+      .field("link_up", &self.is_link_up())
+      .finish()
+  }
+}
+
+impl Eth {
+  /// Install the Ethernet driver using the specified [`EthConfig`](struct.EthConfig.html)
+  /// and start it, if it is not already in use.
+  pub fn start(config: EthConfig) -> Result<Eth, EspError> {
+    if ETH_ACTIVE.compare_and_swap(false, true, SeqCst) {
+      return Err(EspError { code: ESP_ERR_INVALID_STATE as esp_err_t })
+    }
+
+    initialize_network_interface();
+    event_loop_create_default();
+
+    let mut eth = Eth {
+      config,
+      mac: ptr::null_mut(),
+      phy: ptr::null_mut(),
+      handle: ptr::null_mut(),
+      netif: ptr::null_mut(),
+      glue: ptr::null_mut(),
+      link_up: Arc::new(AtomicBool::new(false)),
+      link_handler: None,
+    };
+
+    // On error, everything created so far is cleaned up when `eth` is dropped.
+    eth.install()?;
+    Ok(eth)
+  }
+
+  fn install(&mut self) -> Result<(), EspError> {
+    let no_mem = || EspError { code: ESP_ERR_NO_MEM as esp_err_t };
+
+    let mut mac_config: eth_mac_config_t = unsafe { mem::zeroed() };
+    mac_config.sw_reset_timeout_ms = 100;
+    mac_config.rx_task_stack_size = 4096;
+    mac_config.rx_task_prio = 15;
+    mac_config.smi_mdc_gpio_num = self.config.mdc_pin as i32;
+    mac_config.smi_mdio_gpio_num = self.config.mdio_pin as i32;
+
+    let mut phy_config: eth_phy_config_t = unsafe { mem::zeroed() };
+    phy_config.phy_addr = self.config.phy_addr;
+    phy_config.reset_timeout_ms = 100;
+    phy_config.autonego_timeout_ms = 4000;
+    phy_config.reset_gpio_num = self.config.phy_reset_pin.map_or(-1, |pin| pin as i32);
+
+    self.mac = unsafe { esp_eth_mac_new_esp32(&mac_config) };
+    if self.mac.is_null() {
+      return Err(no_mem())
+    }
+
+    self.phy = self.config.phy.create(&phy_config);
+    if self.phy.is_null() {
+      return Err(no_mem())
+    }
+
+    let mut eth_config: esp_eth_config_t = unsafe { mem::zeroed() };
+    eth_config.mac = self.mac;
+    eth_config.phy = self.phy;
+    eth_config.check_link_period_ms = 2000;
+
+    esp_ok!(esp_eth_driver_install(&eth_config, &mut self.handle))?;
+
+    let netif_config = esp_netif_config_t {
+      base: unsafe { &_g_esp_netif_inherent_eth_config },
+      driver: ptr::null(),
+      stack: unsafe { _g_esp_netif_netstack_default_eth },
+    };
+
+    self.netif = unsafe { esp_netif_new(&netif_config) };
+    if self.netif.is_null() {
+      return Err(no_mem())
+    }
+
+    esp_ok!(esp_eth_set_default_handlers(self.netif as *mut _))?;
+
+    self.glue = unsafe { esp_eth_new_netif_glue(self.handle) };
+    esp_ok!(esp_netif_attach(self.netif, self.glue))?;
+
+    let handle = self.handle as usize;
+    let link_up = Arc::clone(&self.link_up);
+    self.link_handler = Some(EventHandler::register(unsafe { ETH_EVENT }, ESP_EVENT_ANY_ID, move |_, event_id, event_data| {
+      if unsafe { *(event_data as *const esp_eth_handle_t) } as usize != handle {
+        return
+      }
+
+      if event_id == eth_event_t::ETHERNET_EVENT_CONNECTED as i32 {
+        link_up.store(true, SeqCst);
+      } else if event_id == eth_event_t::ETHERNET_EVENT_DISCONNECTED as i32 || event_id == eth_event_t::ETHERNET_EVENT_STOP as i32 {
+        link_up.store(false, SeqCst);
+      }
+    })?);
+
+    ETH_PTR.store(self.netif as usize, SeqCst);
+
+    esp_ok!(esp_eth_start(self.handle))
+  }
+
+  pub fn config(&self) -> &EthConfig {
+    &self.config
+  }
+
+  /// Whether the Ethernet link is currently up.
+  pub fn is_link_up(&self) -> bool {
+    self.link_up.load(SeqCst)
+  }
+
+  /// IP information of the Ethernet [`Interface`](../interface/enum.Interface.html).
+  ///
+  /// Returns `None` until an IP address was obtained.
+  pub fn ip_info(&self) -> Option<IpInfo> {
+    Interface::Eth.ip_info()
+  }
+}
+
+impl Drop for Eth {
+  /// Stops the Ethernet driver and destroys its network interface.
+  fn drop(&mut self) {
+    ETH_PTR.store(0, SeqCst);
+
+    unsafe {
+      if !self.handle.is_null() {
+        esp_eth_stop(self.handle);
+      }
+
+      self.link_handler = None;
+
+      if !self.netif.is_null() {
+        esp_eth_clear_default_handlers(self.netif as *mut _);
+      }
+
+      if !self.glue.is_null() {
+        esp_eth_del_netif_glue(self.glue);
+      }
+
+      if !self.handle.is_null() {
+        esp_eth_driver_uninstall(self.handle);
+      }
+
+      if let Some(phy) = self.phy.as_mut() {
+        if let Some(del) = phy.del {
+          del(phy);
+        }
+      }
+
+      if let Some(mac) = self.mac.as_mut() {
+        if let Some(del) = mac.del {
+          del(mac);
+        }
+      }
+
+      if !self.netif.is_null() {
+        esp_netif_destroy(self.netif);
+      }
+    }
+
+    ETH_ACTIVE.store(false, SeqCst);
+  }
+}
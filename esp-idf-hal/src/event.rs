@@ -0,0 +1,75 @@
+use core::fmt;
+use core::ptr;
+use alloc::boxed::Box;
+
+use esp_idf_bindgen::{
+  esp_event_base_t,
+  esp_event_handler_instance_t,
+  esp_event_handler_instance_register,
+  esp_event_handler_instance_unregister,
+};
+
+use crate::EspError;
+
+type Callback = dyn FnMut(esp_event_base_t, i32, *mut libc::c_void) + Send;
+
+/// An event handler registered on the default event loop, which is unregistered when dropped.
+pub(crate) struct EventHandler {
+  event_base: esp_event_base_t,
+  event_id: i32,
+  instance: esp_event_handler_instance_t,
+  callback: *mut Box<Callback>,
+}
+
+unsafe impl Send for EventHandler {}
+unsafe impl Sync for EventHandler {}
+
+impl fmt::Debug for EventHandler {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("EventHandler")
+      .field("event_base", &self.event_base)
+      .field("event_id", &self.event_id)
+      .finish()
+  }
+}
+
+impl EventHandler {
+  /// Register `callback` for the given event on the default event loop.
+  ///
+  /// The callback is called on the event loop task with the event base, event ID and event data.
+  pub fn register<F>(event_base: esp_event_base_t, event_id: i32, callback: F) -> Result<Self, EspError>
+  where
+    F: FnMut(esp_event_base_t, i32, *mut libc::c_void) + Send + 'static,
+  {
+    let callback: Box<Box<Callback>> = Box::new(Box::new(callback));
+    let callback = Box::into_raw(callback);
+
+    let mut instance = ptr::null_mut();
+
+    if let Err(err) = esp_ok!(esp_event_handler_instance_register(
+      event_base, event_id, Some(trampoline), callback as *mut _, &mut instance,
+    )) {
+      drop(unsafe { Box::from_raw(callback) });
+      return Err(err)
+    }
+
+    Ok(Self { event_base, event_id, instance, callback })
+  }
+}
+
+impl Drop for EventHandler {
+  fn drop(&mut self) {
+    let _ = esp_ok!(esp_event_handler_instance_unregister(self.event_base, self.event_id, self.instance));
+    drop(unsafe { Box::from_raw(self.callback) });
+  }
+}
+
+extern "C" fn trampoline(
+  event_handler_arg: *mut libc::c_void,
+  event_base: esp_event_base_t,
+  event_id: i32,
+  event_data: *mut libc::c_void,
+) {
+  let callback = unsafe { &mut *(event_handler_arg as *mut Box<Callback>) };
+  callback(event_base, event_id, event_data);
+}
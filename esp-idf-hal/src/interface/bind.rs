@@ -0,0 +1,112 @@
+use core::mem::{self, MaybeUninit};
+use core::ptr;
+use std::io;
+use std::net::{TcpListener, TcpStream, UdpSocket};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::tcpip_adapter_get_netif;
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::esp_netif_get_netif_impl;
+use esp_idf_bindgen::{ifreq, lwip_setsockopt, netif, SOL_SOCKET, SO_BINDTODEVICE};
+
+use super::Interface;
+
+impl Interface {
+  fn lwip_netif(&self) -> *const netif {
+    #[cfg(target_device = "esp8266")]
+    {
+      let mut netif = ptr::null_mut();
+      if esp_ok!(tcpip_adapter_get_netif(self.adapter(), &mut netif)).is_err() {
+        return ptr::null()
+      }
+      netif as *const netif
+    }
+
+    #[cfg(target_device = "esp32")]
+    {
+      let ptr = self.ptr();
+      if ptr.is_null() {
+        return ptr::null()
+      }
+      unsafe { esp_netif_get_netif_impl(ptr) as *const netif }
+    }
+  }
+
+  /// The name of the lwIP network interface, e.g. `st1`, or `None` if it does not currently exist.
+  ///
+  /// The number in the name changes whenever the network interface is re-created, so this
+  /// can be used to find out whether a socket has to be bound to this interface again.
+  pub fn netif_name(&self) -> Option<String> {
+    let netif = unsafe { self.lwip_netif().as_ref() }?;
+    Some(format!("{}{}{}", netif.name[0] as u8 as char, netif.name[1] as u8 as char, netif.num))
+  }
+
+  /// An `ifreq` with the name of the lwIP network interface, as used by `SO_BINDTODEVICE`.
+  fn lwip_name(&self) -> io::Result<ifreq> {
+    let name = self.netif_name().ok_or_else(|| {
+      io::Error::new(io::ErrorKind::NotFound, format!("interface {:?} does not currently exist", self))
+    })?;
+
+    let mut ifreq = unsafe { MaybeUninit::<ifreq>::zeroed().assume_init() };
+    if name.len() >= ifreq.ifr_name.len() {
+      return Err(io::Error::new(io::ErrorKind::InvalidInput, "interface name too long"))
+    }
+
+    for (dst, &src) in ifreq.ifr_name.iter_mut().zip(name.as_bytes()) {
+      *dst = src as _;
+    }
+
+    Ok(ifreq)
+  }
+}
+
+fn bind_fd_to_interface(fd: RawFd, interface: Interface) -> io::Result<()> {
+  let ifreq = interface.lwip_name()?;
+
+  let ret = unsafe {
+    lwip_setsockopt(
+      fd, SOL_SOCKET as _, SO_BINDTODEVICE as _,
+      &ifreq as *const ifreq as *const _, mem::size_of::<ifreq>() as _,
+    )
+  };
+
+  if ret != 0 {
+    return Err(io::Error::last_os_error())
+  }
+
+  Ok(())
+}
+
+/// Extension trait for binding sockets to an [`Interface`](enum.Interface.html).
+///
+/// A bound socket only sends and receives packets on the given interface, e.g. a server
+/// bound to [`Interface::Ap`](enum.Interface.html#variant.Ap) is not reachable
+/// from the network the station is connected to.
+pub trait BindToInterface {
+  /// Bind this socket to `interface`.
+  ///
+  /// The network interface must currently exist, i.e. the corresponding WiFi mode
+  /// must be started. The socket stays bound to this network interface, so it has to be
+  /// bound again if the interface is re-created, e.g. when the access point is restarted,
+  /// which can be detected using [`Interface::netif_name`](enum.Interface.html#method.netif_name).
+  fn bind_to_interface(&self, interface: Interface) -> io::Result<()>;
+}
+
+impl BindToInterface for UdpSocket {
+  fn bind_to_interface(&self, interface: Interface) -> io::Result<()> {
+    bind_fd_to_interface(self.as_raw_fd(), interface)
+  }
+}
+
+impl BindToInterface for TcpListener {
+  fn bind_to_interface(&self, interface: Interface) -> io::Result<()> {
+    bind_fd_to_interface(self.as_raw_fd(), interface)
+  }
+}
+
+impl BindToInterface for TcpStream {
+  fn bind_to_interface(&self, interface: Interface) -> io::Result<()> {
+    bind_fd_to_interface(self.as_raw_fd(), interface)
+  }
+}
@@ -0,0 +1,337 @@
+use core::mem;
+#[cfg(all(target_device = "esp32", feature = "dhcps-captive-portal"))]
+use core::ptr;
+use std::ffi::CString;
+use std::net::Ipv4Addr;
+#[cfg(all(target_device = "esp32", feature = "dhcps-captive-portal"))]
+use std::sync::atomic::{AtomicPtr, Ordering::SeqCst};
+use std::time::Duration;
+
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::{
+  ip4_addr_t,
+  dhcps_lease_t,
+  dhcps_offer_t,
+  OFFER_DNS,
+  tcpip_adapter_dhcps_start,
+  tcpip_adapter_dhcps_stop,
+  tcpip_adapter_dhcps_option,
+  tcpip_adapter_dhcp_option_mode_t as option_mode_t,
+  tcpip_adapter_dhcp_option_id_t as option_id_t,
+  tcpip_adapter_set_ip_info,
+  tcpip_adapter_ip_info_t as ip_info_t,
+  ESP_ERR_TCPIP_ADAPTER_DHCP_ALREADY_STOPPED as DHCP_ALREADY_STOPPED,
+};
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{
+  ip4_addr_t,
+  esp_ip4_addr_t,
+  dhcps_lease_t,
+  dhcps_offer_t,
+  OFFER_DNS,
+  esp_netif_dhcps_start,
+  esp_netif_dhcps_stop,
+  esp_netif_dhcps_option,
+  esp_netif_dhcp_option_mode_t as option_mode_t,
+  esp_netif_dhcp_option_id_t as option_id_t,
+  esp_netif_set_ip_info,
+  esp_netif_ip_info_t as ip_info_t,
+  ESP_ERR_ESP_NETIF_DHCP_ALREADY_STOPPED as DHCP_ALREADY_STOPPED,
+};
+use esp_idf_bindgen::{esp_err_t, ESP_ERR_INVALID_ARG, ESP_ERR_NOT_SUPPORTED};
+
+use crate::EspError;
+use super::{Interface, DnsType};
+
+/// A captive portal advertised to clients using DHCP option 114 ([RFC 8910](https://tools.ietf.org/html/rfc8910)).
+#[derive(Debug, Clone)]
+pub enum CaptivePortal {
+  /// `http://<ip>/`, where `<ip>` is the IP address of the interface.
+  Ip,
+  /// A custom URI.
+  Uri(String),
+}
+
+/// DHCP server configuration for an [`Interface`](enum.Interface.html).
+///
+/// Lease times are rounded down to whole minutes, with a minimum of one minute.
+#[derive(Debug, Clone)]
+pub struct DhcpServerConfig {
+  ip: Ipv4Addr,
+  netmask: Ipv4Addr,
+  lease_range: Option<(Ipv4Addr, Ipv4Addr)>,
+  lease_time: Option<Duration>,
+  dns: Option<Ipv4Addr>,
+  captive_portal: Option<CaptivePortal>,
+}
+
+impl DhcpServerConfig {
+  pub fn ip(&self) -> &Ipv4Addr {
+    &self.ip
+  }
+
+  pub fn netmask(&self) -> &Ipv4Addr {
+    &self.netmask
+  }
+
+  pub fn lease_range(&self) -> Option<(&Ipv4Addr, &Ipv4Addr)> {
+    self.lease_range.as_ref().map(|(start, end)| (start, end))
+  }
+
+  pub fn lease_time(&self) -> Option<&Duration> {
+    self.lease_time.as_ref()
+  }
+
+  pub fn dns(&self) -> Option<&Ipv4Addr> {
+    self.dns.as_ref()
+  }
+
+  pub fn captive_portal(&self) -> Option<&CaptivePortal> {
+    self.captive_portal.as_ref()
+  }
+
+  /// The captive portal URI advertised to clients, if any.
+  pub fn captive_portal_uri(&self) -> Option<String> {
+    self.captive_portal.as_ref().map(|captive_portal| match captive_portal {
+      CaptivePortal::Ip => format!("http://{}/", self.ip),
+      CaptivePortal::Uri(uri) => uri.clone(),
+    })
+  }
+
+  pub fn builder() -> DhcpServerConfigBuilder {
+    DhcpServerConfigBuilder::default()
+  }
+}
+
+/// Builder for [`DhcpServerConfig`](struct.DhcpServerConfig.html).
+#[derive(Debug, Clone)]
+pub struct DhcpServerConfigBuilder {
+  ip: Ipv4Addr,
+  netmask: Ipv4Addr,
+  lease_range: Option<(Ipv4Addr, Ipv4Addr)>,
+  lease_time: Option<Duration>,
+  dns: Option<Ipv4Addr>,
+  captive_portal: Option<CaptivePortal>,
+}
+
+impl Default for DhcpServerConfigBuilder {
+  fn default() -> Self {
+    Self {
+      ip: Ipv4Addr::new(192, 168, 4, 1),
+      netmask: Ipv4Addr::new(255, 255, 255, 0),
+      lease_range: None,
+      lease_time: None,
+      dns: None,
+      captive_portal: None,
+    }
+  }
+}
+
+impl DhcpServerConfigBuilder {
+  /// Set the IP address of the interface, which is also used as the gateway for clients.
+  pub fn ip(&mut self, ip: Ipv4Addr) -> &mut Self {
+    self.ip = ip;
+    self
+  }
+
+  pub fn netmask(&mut self, netmask: Ipv4Addr) -> &mut Self {
+    self.netmask = netmask;
+    self
+  }
+
+  /// Set the first and last address (inclusive) handed out to clients.
+  pub fn lease_range(&mut self, start: Ipv4Addr, end: Ipv4Addr) -> &mut Self {
+    self.lease_range = Some((start, end));
+    self
+  }
+
+  pub fn lease_time(&mut self, lease_time: Duration) -> &mut Self {
+    self.lease_time = Some(lease_time);
+    self
+  }
+
+  /// Set the DNS server offered to clients.
+  pub fn dns(&mut self, dns: Ipv4Addr) -> &mut Self {
+    self.dns = Some(dns);
+    self
+  }
+
+  /// Advertise a captive portal to clients.
+  ///
+  /// This requires the `dhcps-captive-portal` feature and an ESP-IDF version whose
+  /// DHCP server supports `ESP_NETIF_CAPTIVEPORTAL_URI`, otherwise applying the
+  /// configuration fails with `ESP_ERR_NOT_SUPPORTED`.
+  pub fn captive_portal(&mut self, captive_portal: CaptivePortal) -> &mut Self {
+    self.captive_portal = Some(captive_portal);
+    self
+  }
+
+  pub fn build(&self) -> DhcpServerConfig {
+    DhcpServerConfig {
+      ip: self.ip,
+      netmask: self.netmask,
+      lease_range: self.lease_range,
+      lease_time: self.lease_time,
+      dns: self.dns,
+      captive_portal: self.captive_portal.clone(),
+    }
+  }
+}
+
+#[inline]
+fn ip4_addr(ip: Ipv4Addr) -> ip4_addr_t {
+  ip4_addr_t { addr: u32::from(ip).to_be() }
+}
+
+impl DhcpServerConfig {
+  #[cfg(target_device = "esp8266")]
+  fn ip_info(&self) -> ip_info_t {
+    ip_info_t { ip: ip4_addr(self.ip), netmask: ip4_addr(self.netmask), gw: ip4_addr(self.ip) }
+  }
+
+  #[cfg(target_device = "esp32")]
+  fn ip_info(&self) -> ip_info_t {
+    let addr = |ip: Ipv4Addr| esp_ip4_addr_t { addr: u32::from(ip).to_be() };
+    ip_info_t { ip: addr(self.ip), netmask: addr(self.netmask), gw: addr(self.ip) }
+  }
+
+  fn lease(&self) -> Option<dhcps_lease_t> {
+    self.lease_range.map(|(start, end)| dhcps_lease_t {
+      enable: true,
+      start_ip: ip4_addr(start),
+      end_ip: ip4_addr(end),
+    })
+  }
+
+  fn captive_portal_cstring(&self) -> Result<Option<CString>, EspError> {
+    let uri = match self.captive_portal_uri() {
+      Some(uri) => uri,
+      None => return Ok(None),
+    };
+
+    if cfg!(not(all(target_device = "esp32", feature = "dhcps-captive-portal"))) {
+      return Err(EspError { code: ESP_ERR_NOT_SUPPORTED as esp_err_t })
+    }
+
+    CString::new(uri).map(Some).map_err(|_| EspError { code: ESP_ERR_INVALID_ARG as esp_err_t })
+  }
+
+  /// Lease time in units of `CONFIG_LWIP_DHCPS_LEASE_UNIT`, i.e. minutes.
+  fn lease_time_units(&self) -> Option<u32> {
+    self.lease_time.map(|lease_time| (lease_time.as_secs() / 60).max(1) as u32)
+  }
+}
+
+impl Interface {
+  /// Configure the IP address and DHCP server of this interface.
+  ///
+  /// The DHCP server is restarted with the new configuration. This is only supported
+  /// by interfaces running a DHCP server, i.e. [`Interface::Ap`](enum.Interface.html#variant.Ap).
+  #[cfg(target_device = "esp8266")]
+  pub fn set_dhcp_server_config(&self, config: &DhcpServerConfig) -> Result<(), EspError> {
+    let adapter = self.adapter();
+
+    config.captive_portal_cstring()?;
+
+    match esp_ok!(tcpip_adapter_dhcps_stop(adapter)) {
+      Err(err) if err.code != DHCP_ALREADY_STOPPED as esp_err_t => return Err(err),
+      _ => (),
+    }
+
+    esp_ok!(tcpip_adapter_set_ip_info(adapter, &config.ip_info()))?;
+
+    if let Some(mut lease) = config.lease() {
+      esp_ok!(tcpip_adapter_dhcps_option(
+        option_mode_t::TCPIP_ADAPTER_OP_SET, option_id_t::TCPIP_ADAPTER_REQUESTED_IP_ADDRESS,
+        &mut lease as *mut _ as *mut _, mem::size_of::<dhcps_lease_t>() as u32,
+      ))?;
+    }
+
+    if let Some(mut lease_time) = config.lease_time_units() {
+      esp_ok!(tcpip_adapter_dhcps_option(
+        option_mode_t::TCPIP_ADAPTER_OP_SET, option_id_t::TCPIP_ADAPTER_IP_ADDRESS_LEASE_TIME,
+        &mut lease_time as *mut _ as *mut _, mem::size_of::<u32>() as u32,
+      ))?;
+    }
+
+    if let Some(dns) = config.dns {
+      self.set_dns(DnsType::Main, dns.into())?;
+
+      let mut offer: dhcps_offer_t = OFFER_DNS as _;
+      esp_ok!(tcpip_adapter_dhcps_option(
+        option_mode_t::TCPIP_ADAPTER_OP_SET, option_id_t::TCPIP_ADAPTER_DOMAIN_NAME_SERVER,
+        &mut offer as *mut _ as *mut _, mem::size_of::<dhcps_offer_t>() as u32,
+      ))?;
+    }
+
+    esp_ok!(tcpip_adapter_dhcps_start(adapter))
+  }
+
+  /// Configure the IP address and DHCP server of this interface.
+  ///
+  /// The DHCP server is restarted with the new configuration. This is only supported
+  /// by interfaces running a DHCP server, i.e. [`Interface::Ap`](enum.Interface.html#variant.Ap).
+  #[cfg(target_device = "esp32")]
+  pub fn set_dhcp_server_config(&self, config: &DhcpServerConfig) -> Result<(), EspError> {
+    let netif = self.ptr();
+
+    let captive_portal_uri = config.captive_portal_cstring()?;
+
+    match esp_ok!(esp_netif_dhcps_stop(netif)) {
+      Err(err) if err.code != DHCP_ALREADY_STOPPED as esp_err_t => return Err(err),
+      _ => (),
+    }
+
+    esp_ok!(esp_netif_set_ip_info(netif, &config.ip_info()))?;
+
+    if let Some(mut lease) = config.lease() {
+      esp_ok!(esp_netif_dhcps_option(
+        netif, option_mode_t::ESP_NETIF_OP_SET, option_id_t::ESP_NETIF_REQUESTED_IP_ADDRESS,
+        &mut lease as *mut _ as *mut _, mem::size_of::<dhcps_lease_t>() as u32,
+      ))?;
+    }
+
+    if let Some(mut lease_time) = config.lease_time_units() {
+      esp_ok!(esp_netif_dhcps_option(
+        netif, option_mode_t::ESP_NETIF_OP_SET, option_id_t::ESP_NETIF_IP_ADDRESS_LEASE_TIME,
+        &mut lease_time as *mut _ as *mut _, mem::size_of::<u32>() as u32,
+      ))?;
+    }
+
+    if let Some(dns) = config.dns {
+      self.set_dns(DnsType::Main, dns.into())?;
+
+      let mut offer: dhcps_offer_t = OFFER_DNS as _;
+      esp_ok!(esp_netif_dhcps_option(
+        netif, option_mode_t::ESP_NETIF_OP_SET, option_id_t::ESP_NETIF_DOMAIN_NAME_SERVER,
+        &mut offer as *mut _ as *mut _, mem::size_of::<dhcps_offer_t>() as u32,
+      ))?;
+    }
+
+    #[cfg(feature = "dhcps-captive-portal")]
+    if let Some(uri) = captive_portal_uri {
+      // The DHCP server only stores a pointer to the URI, so it has to outlive the server configuration.
+      static CAPTIVE_PORTAL_URI: AtomicPtr<libc::c_char> = AtomicPtr::new(ptr::null_mut());
+
+      let len = uri.as_bytes().len();
+      let uri = uri.into_raw();
+
+      let res = esp_ok!(esp_netif_dhcps_option(
+        netif, option_mode_t::ESP_NETIF_OP_SET, option_id_t::ESP_NETIF_CAPTIVEPORTAL_URI,
+        uri as *mut _, len as u32,
+      ));
+
+      let old_uri = if res.is_ok() { CAPTIVE_PORTAL_URI.swap(uri, SeqCst) } else { uri };
+      if !old_uri.is_null() {
+        drop(unsafe { CString::from_raw(old_uri) });
+      }
+
+      res?;
+    }
+
+    #[cfg(not(feature = "dhcps-captive-portal"))]
+    drop(captive_portal_uri);
+
+    esp_ok!(esp_netif_dhcps_start(netif))
+  }
+}
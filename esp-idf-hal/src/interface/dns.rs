@@ -0,0 +1,164 @@
+use core::mem::{self, MaybeUninit};
+use std::net::IpAddr;
+#[cfg(target_device = "esp8266")]
+use std::net::Ipv4Addr;
+#[cfg(target_device = "esp32")]
+use std::net::Ipv6Addr;
+
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::{
+  tcpip_adapter_get_dns_info,
+  tcpip_adapter_set_dns_info,
+  tcpip_adapter_dns_type_t as dns_type_t,
+  tcpip_adapter_dns_info_t as dns_info_t,
+};
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{
+  esp_netif_get_dns_info,
+  esp_netif_set_dns_info,
+  esp_netif_dns_type_t as dns_type_t,
+  esp_netif_dns_info_t as dns_info_t,
+  esp_ip_addr_t,
+  ESP_IPADDR_TYPE_V4,
+  ESP_IPADDR_TYPE_V6,
+};
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::{esp_err_t, ESP_ERR_INVALID_ARG};
+
+use crate::EspError;
+use super::Interface;
+
+/// Type of a DNS server used by an [`Interface`](enum.Interface.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DnsType {
+  /// The main DNS server, usually provided by DHCP.
+  Main,
+  /// The backup DNS server, usually provided by DHCP.
+  Backup,
+  /// A fallback DNS server which is never overwritten by DHCP.
+  Fallback,
+}
+
+impl DnsType {
+  pub(crate) const ALL: [DnsType; 3] = [DnsType::Main, DnsType::Backup, DnsType::Fallback];
+
+  pub(crate) fn index(self) -> usize {
+    self as usize
+  }
+}
+
+#[cfg(target_device = "esp8266")]
+impl From<DnsType> for dns_type_t {
+  fn from(dns_type: DnsType) -> Self {
+    match dns_type {
+      DnsType::Main => dns_type_t::TCPIP_ADAPTER_DNS_MAIN,
+      DnsType::Backup => dns_type_t::TCPIP_ADAPTER_DNS_BACKUP,
+      DnsType::Fallback => dns_type_t::TCPIP_ADAPTER_DNS_FALLBACK,
+    }
+  }
+}
+
+#[cfg(target_device = "esp32")]
+impl From<DnsType> for dns_type_t {
+  fn from(dns_type: DnsType) -> Self {
+    match dns_type {
+      DnsType::Main => dns_type_t::ESP_NETIF_DNS_MAIN,
+      DnsType::Backup => dns_type_t::ESP_NETIF_DNS_BACKUP,
+      DnsType::Fallback => dns_type_t::ESP_NETIF_DNS_FALLBACK,
+    }
+  }
+}
+
+#[cfg(target_device = "esp32")]
+pub(crate) fn ip_addr_from_native(ip: &esp_ip_addr_t) -> Option<IpAddr> {
+  let ip = unsafe {
+    match ip.type_ as u32 {
+      ESP_IPADDR_TYPE_V4 => IpAddr::V4(u32::from_be(ip.u_addr.ip4.addr).into()),
+      ESP_IPADDR_TYPE_V6 => IpAddr::V6(ipv6_addr_from_native(&ip.u_addr.ip6.addr)),
+      _ => return None,
+    }
+  };
+
+  if ip.is_unspecified() { None } else { Some(ip) }
+}
+
+/// Convert an IPv6 address stored as four words in network byte order.
+#[cfg(target_device = "esp32")]
+pub(crate) fn ipv6_addr_from_native(addr: &[u32; 4]) -> Ipv6Addr {
+  let mut octets = [0; 16];
+  for (chunk, word) in octets.chunks_mut(4).zip(addr.iter()) {
+    chunk.copy_from_slice(&word.to_ne_bytes());
+  }
+  Ipv6Addr::from(octets)
+}
+
+#[cfg(target_device = "esp32")]
+pub(crate) fn ipv6_addr_to_native(ip: &Ipv6Addr) -> [u32; 4] {
+  let octets = ip.octets();
+  let mut addr = [0; 4];
+  for (word, chunk) in addr.iter_mut().zip(octets.chunks(4)) {
+    *word = u32::from_ne_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+  }
+  addr
+}
+
+impl Interface {
+  /// Get the DNS server of the given type.
+  ///
+  /// Returns `None` if no DNS server of this type is set.
+  #[cfg(target_device = "esp8266")]
+  pub fn dns(&self, dns_type: DnsType) -> Result<Option<IpAddr>, EspError> {
+    let mut dns_info = MaybeUninit::<dns_info_t>::uninit();
+    esp_ok!(tcpip_adapter_get_dns_info(self.adapter(), dns_type.into(), dns_info.as_mut_ptr()))?;
+    let dns_info = unsafe { dns_info.assume_init() };
+
+    let ip = Ipv4Addr::from(u32::from_be(dns_info.ip.addr));
+    Ok(if ip.is_unspecified() { None } else { Some(IpAddr::V4(ip)) })
+  }
+
+  /// Get the DNS server of the given type.
+  ///
+  /// Returns `None` if no DNS server of this type is set.
+  #[cfg(target_device = "esp32")]
+  pub fn dns(&self, dns_type: DnsType) -> Result<Option<IpAddr>, EspError> {
+    let mut dns_info = MaybeUninit::<dns_info_t>::uninit();
+    esp_ok!(esp_netif_get_dns_info(self.ptr(), dns_type.into(), dns_info.as_mut_ptr()))?;
+    let dns_info = unsafe { dns_info.assume_init() };
+
+    Ok(ip_addr_from_native(&dns_info.ip))
+  }
+
+  /// Set the DNS server of the given type.
+  ///
+  /// Only IPv4 DNS servers are supported on the ESP8266.
+  #[cfg(target_device = "esp8266")]
+  pub fn set_dns(&self, dns_type: DnsType, ip: IpAddr) -> Result<(), EspError> {
+    let ip = match ip {
+      IpAddr::V4(ip) => ip,
+      IpAddr::V6(_) => return Err(EspError { code: ESP_ERR_INVALID_ARG as esp_err_t }),
+    };
+
+    let mut dns_info: dns_info_t = unsafe { mem::zeroed() };
+    dns_info.ip.addr = u32::from(ip).to_be();
+    esp_ok!(tcpip_adapter_set_dns_info(self.adapter(), dns_type.into(), &mut dns_info))
+  }
+
+  /// Set the DNS server of the given type.
+  #[cfg(target_device = "esp32")]
+  pub fn set_dns(&self, dns_type: DnsType, ip: IpAddr) -> Result<(), EspError> {
+    let mut dns_info: dns_info_t = unsafe { mem::zeroed() };
+
+    match ip {
+      IpAddr::V4(ip) => {
+        dns_info.ip.u_addr.ip4.addr = u32::from(ip).to_be();
+        dns_info.ip.type_ = ESP_IPADDR_TYPE_V4 as _;
+      },
+      IpAddr::V6(ip) => {
+        dns_info.ip.u_addr.ip6.addr = ipv6_addr_to_native(&ip);
+        dns_info.ip.type_ = ESP_IPADDR_TYPE_V6 as _;
+      },
+    }
+
+    esp_ok!(esp_netif_set_dns_info(self.ptr(), dns_type.into(), &mut dns_info))
+  }
+}
@@ -0,0 +1,67 @@
+use core::ptr;
+use std::ffi::{CStr, CString};
+
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::{tcpip_adapter_get_hostname, tcpip_adapter_set_hostname};
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{esp_netif_get_hostname, esp_netif_set_hostname};
+use esp_idf_bindgen::{esp_err_t, ESP_ERR_INVALID_ARG};
+use macaddr::MacAddr6;
+
+use crate::EspError;
+use super::Interface;
+
+const HOSTNAME_MAX_LEN: usize = 32;
+
+/// Check whether `hostname` is a valid hostname label, i.e. 1 to 32 ASCII letters,
+/// digits or hyphens, not starting or ending with a hyphen.
+fn is_valid_hostname(hostname: &str) -> bool {
+  !hostname.is_empty() &&
+    hostname.len() <= HOSTNAME_MAX_LEN &&
+    !hostname.starts_with('-') &&
+    !hostname.ends_with('-') &&
+    hostname.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-')
+}
+
+impl Interface {
+  /// The default hostname for this interface, derived from its MAC address,
+  /// e.g. `esp-a1b2c3`.
+  pub fn default_hostname(&self) -> String {
+    let mac = MacAddr6::from(*self);
+    let mac = mac.as_bytes();
+    format!("esp-{:02x}{:02x}{:02x}", mac[3], mac[4], mac[5])
+  }
+
+  /// Get the hostname of this interface.
+  pub fn hostname(&self) -> Result<String, EspError> {
+    let mut hostname = ptr::null();
+
+    #[cfg(target_device = "esp8266")]
+    esp_ok!(tcpip_adapter_get_hostname(self.adapter(), &mut hostname))?;
+    #[cfg(target_device = "esp32")]
+    esp_ok!(esp_netif_get_hostname(self.ptr(), &mut hostname))?;
+
+    if hostname.is_null() {
+      return Ok(String::new())
+    }
+
+    Ok(unsafe { CStr::from_ptr(hostname) }.to_string_lossy().into_owned())
+  }
+
+  /// Set the hostname of this interface.
+  ///
+  /// The hostname is sent to the DHCP server, so it has to be set before the interface
+  /// obtains an IP address in order to show up on the network.
+  pub fn set_hostname(&self, hostname: &str) -> Result<(), EspError> {
+    if !is_valid_hostname(hostname) {
+      return Err(EspError { code: ESP_ERR_INVALID_ARG as esp_err_t })
+    }
+
+    let hostname = CString::new(hostname).map_err(|_| EspError { code: ESP_ERR_INVALID_ARG as esp_err_t })?;
+
+    #[cfg(target_device = "esp8266")]
+    return esp_ok!(tcpip_adapter_set_hostname(self.adapter(), hostname.as_ptr()));
+    #[cfg(target_device = "esp32")]
+    return esp_ok!(esp_netif_set_hostname(self.ptr(), hostname.as_ptr()));
+  }
+}
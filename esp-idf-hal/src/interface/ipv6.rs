@@ -0,0 +1,190 @@
+use core::future::Future;
+use core::mem::MaybeUninit;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::net::Ipv6Addr;
+use std::sync::{Arc, Mutex};
+
+use esp_idf_bindgen::{
+  esp_err_t,
+  esp_ip6_addr_t,
+  esp_netif_create_ip6_linklocal,
+  esp_netif_get_all_ip6,
+  esp_netif_get_ip6_global,
+  esp_netif_get_ip6_linklocal,
+  esp_netif_t,
+  ip_event_t,
+  ip_event_got_ip6_t,
+  IP_EVENT,
+  LWIP_IPV6_NUM_ADDRESSES,
+};
+
+use crate::EspError;
+use crate::event::EventHandler;
+use super::Interface;
+use super::dns::ipv6_addr_from_native;
+
+/// State of an IPv6 address assigned to an [`Interface`](enum.Interface.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ipv6AddrState {
+  /// The address is the preferred address of its scope and is used for new connections.
+  Preferred,
+  /// The address is valid, but not the preferred address of its scope.
+  Valid,
+}
+
+/// An IPv6 address assigned to an [`Interface`](enum.Interface.html).
+#[derive(Debug, Clone)]
+pub struct Ipv6AddrInfo {
+  addr: Ipv6Addr,
+  state: Ipv6AddrState,
+}
+
+impl Ipv6AddrInfo {
+  pub fn addr(&self) -> &Ipv6Addr {
+    &self.addr
+  }
+
+  pub fn state(&self) -> Ipv6AddrState {
+    self.state
+  }
+
+  /// Whether this is a link-local address, i.e. in `fe80::/10`.
+  pub fn is_link_local(&self) -> bool {
+    self.addr.segments()[0] & 0xffc0 == 0xfe80
+  }
+
+  /// Whether this is a global unicast address, i.e. in `2000::/3`.
+  pub fn is_global(&self) -> bool {
+    self.addr.segments()[0] & 0xe000 == 0x2000
+  }
+
+  /// Convert an `IP_EVENT_GOT_IP6` event, returning `None` for unknown network interfaces.
+  pub(crate) fn from_event(event: &ip_event_got_ip6_t) -> Option<(Interface, Self)> {
+    let interface = Interface::from_ptr(event.esp_netif)?;
+    let addr = ipv6_addr_from_native(&event.ip6_info.ip.addr);
+
+    let info = interface.ipv6_addrs().into_iter()
+      .find(|info| info.addr == addr)
+      .unwrap_or(Self { addr, state: Ipv6AddrState::Preferred });
+
+    Some((interface, info))
+  }
+}
+
+impl Interface {
+  /// Enable IPv6 on this interface by creating a link-local address.
+  ///
+  /// This must be called after the interface is up, e.g. after connecting to an access point.
+  /// Global addresses are then obtained using stateless address autoconfiguration.
+  pub fn enable_ipv6(&self) -> Result<(), EspError> {
+    esp_ok!(esp_netif_create_ip6_linklocal(self.ptr()))
+  }
+
+  /// Get all valid IPv6 addresses of this interface.
+  ///
+  /// Tentative and duplicated addresses are not included.
+  pub fn ipv6_addrs(&self) -> Vec<Ipv6AddrInfo> {
+    let ptr = self.ptr();
+
+    if ptr.is_null() {
+      return Vec::new()
+    }
+
+    let get_preferred = |get: unsafe extern "C" fn(*mut esp_netif_t, *mut esp_ip6_addr_t) -> esp_err_t| {
+      let mut addr = MaybeUninit::<esp_ip6_addr_t>::uninit();
+      esp_ok!(get(ptr, addr.as_mut_ptr())).ok()?;
+      Some(ipv6_addr_from_native(&unsafe { addr.assume_init() }.addr))
+    };
+    let preferred = [get_preferred(esp_netif_get_ip6_linklocal), get_preferred(esp_netif_get_ip6_global)];
+
+    let mut addrs = [esp_ip6_addr_t { addr: [0; 4], zone: 0 }; LWIP_IPV6_NUM_ADDRESSES as usize];
+    let count = unsafe { esp_netif_get_all_ip6(ptr, addrs.as_mut_ptr()) }.max(0) as usize;
+
+    addrs[..count.min(addrs.len())].iter().map(|addr| {
+      let addr = ipv6_addr_from_native(&addr.addr);
+      let state = if preferred.contains(&Some(addr)) { Ipv6AddrState::Preferred } else { Ipv6AddrState::Valid };
+      Ipv6AddrInfo { addr, state }
+    }).collect()
+  }
+
+  /// Get the link-local IPv6 address of this interface.
+  pub fn ipv6_link_local(&self) -> Option<Ipv6AddrInfo> {
+    self.ipv6_addrs().into_iter().find(|addr| addr.is_link_local())
+  }
+
+  /// Get the global IPv6 addresses of this interface.
+  pub fn ipv6_global(&self) -> Vec<Ipv6AddrInfo> {
+    self.ipv6_addrs().into_iter().filter(|addr| addr.is_global()).collect()
+  }
+
+  /// Wait until this interface obtains a global IPv6 address.
+  pub fn wait_ipv6_global(&self) -> Ipv6Future {
+    Ipv6Future::new(*self)
+  }
+}
+
+#[derive(Debug, Default)]
+struct Ipv6FutureState {
+  addr: Option<Ipv6AddrInfo>,
+  waker: Option<Waker>,
+}
+
+/// A future which resolves once an [`Interface`](enum.Interface.html) obtains a global IPv6 address.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub struct Ipv6Future {
+  interface: Interface,
+  state: Arc<Mutex<Ipv6FutureState>>,
+  handler: Result<Option<EventHandler>, EspError>,
+}
+
+impl Ipv6Future {
+  fn new(interface: Interface) -> Self {
+    let state = Arc::new(Mutex::new(Ipv6FutureState::default()));
+
+    let handler_state = Arc::clone(&state);
+
+    let handler = EventHandler::register(unsafe { IP_EVENT }, ip_event_t::IP_EVENT_GOT_IP6 as _, move |_, _, event_data| {
+      let event = unsafe { &*(event_data as *const ip_event_got_ip6_t) };
+
+      let addr = match Ipv6AddrInfo::from_event(event) {
+        Some((event_interface, addr)) if event_interface == interface && addr.is_global() => addr,
+        _ => return,
+      };
+
+      let mut state = handler_state.lock().unwrap();
+      state.addr = Some(addr);
+      if let Some(waker) = state.waker.take() {
+        waker.wake();
+      }
+    });
+
+    Self { interface, state, handler: handler.map(Some) }
+  }
+}
+
+impl Future for Ipv6Future {
+  type Output = Result<Ipv6AddrInfo, EspError>;
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    if let Err(err) = &self.handler {
+      return Poll::Ready(Err(err.clone()))
+    }
+
+    let addr = {
+      let mut state = self.state.lock().unwrap();
+
+      match state.addr.take().or_else(|| self.interface.ipv6_global().into_iter().next()) {
+        Some(addr) => addr,
+        None => {
+          state.waker = Some(cx.waker().clone());
+          return Poll::Pending
+        },
+      }
+    };
+
+    self.handler = Ok(None);
+    Poll::Ready(Ok(addr))
+  }
+}
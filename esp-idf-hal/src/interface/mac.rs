@@ -0,0 +1,138 @@
+use core::fmt;
+use core::mem::MaybeUninit;
+
+use esp_idf_bindgen::{
+  esp_base_mac_addr_set,
+  esp_interface_t,
+  esp_random,
+  esp_wifi_get_mac,
+  esp_wifi_set_mac,
+};
+use macaddr::MacAddr6;
+
+use crate::EspError;
+use super::Interface;
+
+/// Error returned when setting a MAC address.
+#[derive(Debug, Clone)]
+pub enum MacAddrError {
+  /// The MAC address is a multicast address.
+  Multicast,
+  /// The MAC address is a locally administered address.
+  LocallyAdministered,
+  /// The interface is started, its MAC address can only be changed while it is stopped.
+  Started(Interface),
+  /// The MAC address is already used by another interface.
+  InUse(Interface),
+  /// The interface does not support setting its MAC address.
+  Unsupported(Interface),
+  /// An internal error not directly related to the MAC address.
+  Internal(EspError),
+}
+
+impl From<EspError> for MacAddrError {
+  fn from(esp_error: EspError) -> Self {
+    Self::Internal(esp_error)
+  }
+}
+
+impl fmt::Display for MacAddrError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Multicast => "MAC address is a multicast address".fmt(f),
+      Self::LocallyAdministered => "MAC address is a locally administered address".fmt(f),
+      Self::Started(interface) => write!(f, "interface {:?} must be stopped to change its MAC address", interface),
+      Self::InUse(interface) => write!(f, "MAC address is already used by interface {:?}", interface),
+      Self::Unsupported(interface) => write!(f, "interface {:?} does not support setting its MAC address", interface),
+      Self::Internal(esp_error) => esp_error.fmt(f),
+    }
+  }
+}
+
+/// Whether `mac` is a unicast address, i.e. the least significant bit of the first octet is not set.
+pub fn is_unicast(mac: &MacAddr6) -> bool {
+  mac.as_bytes()[0] & 0b01 == 0
+}
+
+/// Whether `mac` is a locally administered address, i.e. the second least significant bit
+/// of the first octet is set.
+pub fn is_locally_administered(mac: &MacAddr6) -> bool {
+  mac.as_bytes()[0] & 0b10 != 0
+}
+
+/// Generate a random locally administered unicast MAC address.
+pub fn random_mac() -> MacAddr6 {
+  let [a, b, c, d] = unsafe { esp_random() }.to_ne_bytes();
+  let [e, f, _, _] = unsafe { esp_random() }.to_ne_bytes();
+
+  MacAddr6::new((a & !0b01) | 0b10, b, c, d, e, f)
+}
+
+impl Interface {
+  /// Set the base MAC address from which the MAC addresses of all interfaces are derived.
+  ///
+  /// This must be called before initializing any interface, i.e. before calling
+  /// [`Wifi::take`](../wifi/struct.Wifi.html#method.take). The base MAC address must be
+  /// universally administered, since locally administered addresses for the other
+  /// interfaces are derived from it by setting that bit.
+  pub fn set_base_mac(mac: MacAddr6) -> Result<(), MacAddrError> {
+    if !is_unicast(&mac) {
+      return Err(MacAddrError::Multicast)
+    }
+
+    if is_locally_administered(&mac) {
+      return Err(MacAddrError::LocallyAdministered)
+    }
+
+    esp_ok!(esp_base_mac_addr_set(mac.as_bytes().as_ptr() as *mut _))?;
+    Ok(())
+  }
+
+  fn wifi_interface(&self) -> Option<esp_interface_t> {
+    match self {
+      Self::Sta => Some(esp_interface_t::ESP_IF_WIFI_STA),
+      Self::Ap => Some(esp_interface_t::ESP_IF_WIFI_AP),
+      #[cfg(target_device = "esp32")]
+      _ => None,
+    }
+  }
+
+  /// Get the MAC address currently used by this interface.
+  ///
+  /// In contrast to [`MacAddr6::from`](#impl-From<Interface>), this includes MAC addresses
+  /// set using [`set_mac`](#method.set_mac).
+  pub fn mac(&self) -> Result<MacAddr6, EspError> {
+    let wifi_interface = match self.wifi_interface() {
+      Some(wifi_interface) => wifi_interface,
+      None => return Ok(MacAddr6::from(*self)),
+    };
+
+    let mut mac = MaybeUninit::<MacAddr6>::uninit();
+    esp_ok!(esp_wifi_get_mac(wifi_interface, mac.as_mut_ptr() as *mut _))?;
+    Ok(unsafe { mac.assume_init() })
+  }
+
+  /// Set the MAC address of this interface.
+  ///
+  /// WiFi must be initialized and its mode must include this interface, but it must not be
+  /// started yet. The station and access point interfaces cannot share the same MAC address.
+  pub fn set_mac(&self, mac: MacAddr6) -> Result<(), MacAddrError> {
+    if !is_unicast(&mac) {
+      return Err(MacAddrError::Multicast)
+    }
+
+    let wifi_interface = self.wifi_interface().ok_or(MacAddrError::Unsupported(*self))?;
+
+    let other = match self {
+      Self::Sta => Self::Ap,
+      _ => Self::Sta,
+    };
+
+    if other.mac().ok() == Some(mac) {
+      return Err(MacAddrError::InUse(other))
+    }
+
+    esp_ok!(esp_wifi_set_mac(wifi_interface, mac.as_bytes().as_ptr() as *mut _))?;
+    Ok(())
+  }
+}
@@ -0,0 +1,337 @@
+use std::mem::MaybeUninit;
+use std::net::{IpAddr, Ipv4Addr};
+#[cfg(target_device = "esp32")]
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+#[cfg(target_device = "esp32")]
+use std::{ptr, thread};
+
+use esp_idf_bindgen::{esp_mac_type_t, esp_read_mac};
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::{tcpip_adapter_get_ip_info, tcpip_adapter_if_t, tcpip_adapter_ip_info_t as ip_info_t};
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{esp_netif_get_ip_info, esp_netif_ip_info_t as ip_info_t, esp_netif_t, esp_netif_create_default_wifi_ap, esp_netif_create_default_wifi_sta};
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{esp_netif_destroy, esp_wifi_clear_default_wifi_driver_and_handlers, esp_err_t, ESP_FAIL, ESP_ERR_NOT_SUPPORTED};
+use macaddr::{MacAddr, MacAddr6};
+
+use crate::EspError;
+
+mod bind;
+pub use bind::*;
+
+mod dhcps;
+pub use dhcps::*;
+
+mod dns;
+pub use dns::*;
+
+mod hostname;
+
+mod mac;
+pub use mac::*;
+
+mod napt;
+pub use napt::*;
+
+#[cfg(target_device = "esp32")]
+mod ipv6;
+#[cfg(target_device = "esp32")]
+pub use ipv6::*;
+
+#[cfg(target_device = "esp32")]
+mod stats;
+#[cfg(target_device = "esp32")]
+pub use stats::*;
+
+#[cfg(target_device = "esp32")]
+static AP_NETIF: NetIfSlot = NetIfSlot::new();
+#[cfg(target_device = "esp32")]
+static STA_NETIF: NetIfSlot = NetIfSlot::new();
+
+/// Reference-counted storage for a lazily created `esp_netif_t`.
+#[cfg(target_device = "esp32")]
+struct NetIfSlot {
+  lock: AtomicBool,
+  ptr: AtomicUsize,
+  refs: AtomicUsize,
+}
+
+#[cfg(target_device = "esp32")]
+impl NetIfSlot {
+  const fn new() -> Self {
+    Self { lock: AtomicBool::new(false), ptr: AtomicUsize::new(0), refs: AtomicUsize::new(0) }
+  }
+
+  fn locked<T>(&self, f: impl FnOnce() -> T) -> T {
+    while self.lock.compare_and_swap(false, true, Ordering::SeqCst) {
+      thread::yield_now();
+    }
+
+    let res = f();
+    self.lock.store(false, Ordering::SeqCst);
+    res
+  }
+
+  fn acquire(&self, create: impl FnOnce() -> *mut esp_netif_t) -> Result<(), EspError> {
+    self.locked(|| {
+      if self.refs.load(Ordering::SeqCst) == 0 {
+        let ptr = create();
+
+        if ptr.is_null() {
+          return Err(EspError { code: ESP_FAIL as esp_err_t })
+        }
+
+        self.ptr.store(ptr as usize, Ordering::SeqCst);
+      }
+
+      self.refs.fetch_add(1, Ordering::SeqCst);
+      Ok(())
+    })
+  }
+
+  fn release(&self, destroy: impl FnOnce(*mut esp_netif_t)) {
+    self.locked(|| {
+      if self.refs.fetch_sub(1, Ordering::SeqCst) == 1 {
+        destroy(self.ptr.swap(0, Ordering::SeqCst) as *mut esp_netif_t);
+      }
+    })
+  }
+
+  fn ptr(&self) -> *mut esp_netif_t {
+    self.ptr.load(Ordering::SeqCst) as *mut esp_netif_t
+  }
+}
+
+/// An owned handle to the network interface of an [`Interface`](enum.Interface.html).
+///
+/// The underlying network interface is created when the first handle is acquired
+/// and destroyed when the last handle is dropped.
+#[derive(Debug)]
+pub struct NetIf {
+  interface: Interface,
+}
+
+impl NetIf {
+  pub fn interface(&self) -> Interface {
+    self.interface
+  }
+}
+
+#[cfg(target_device = "esp8266")]
+impl Drop for NetIf {
+  fn drop(&mut self) {}
+}
+
+#[cfg(target_device = "esp32")]
+impl Drop for NetIf {
+  fn drop(&mut self) {
+    let destroy = |ptr: *mut esp_netif_t| unsafe {
+      esp_wifi_clear_default_wifi_driver_and_handlers(ptr as *mut _);
+      esp_netif_destroy(ptr);
+    };
+
+    match self.interface {
+      Interface::Ap => AP_NETIF.release(destroy),
+      Interface::Sta => STA_NETIF.release(destroy),
+      _ => unreachable!(),
+    }
+  }
+}
+
+/// Enumeration of all available interfaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Interface {
+  /// WiFi interface in station mode.
+  Sta,
+  /// WiFi interface in access point mode.
+  Ap,
+  /// Bluetooth interface.
+  #[cfg(target_device = "esp32")]
+  Bt,
+  /// Ethernet interface.
+  #[cfg(target_device = "esp32")]
+  Eth,
+}
+
+impl Interface {
+  /// Get the IP information of this interface.
+  ///
+  /// Returns `None` if the interface has no IP address yet.
+  #[cfg(target_device = "esp8266")]
+  pub fn ip_info(&self) -> Option<IpInfo> {
+    let mut ip_info = MaybeUninit::<ip_info_t>::uninit();
+    esp_ok!(tcpip_adapter_get_ip_info(self.adapter(), ip_info.as_mut_ptr())).ok()?;
+    let ip_info = unsafe { IpInfo::from_native_unchecked(ip_info.assume_init()) };
+
+    if ip_info.ip.is_unspecified() {
+      return None
+    }
+
+    Some(ip_info.with_dns(*self))
+  }
+
+  #[cfg(target_device = "esp8266")]
+  fn adapter(&self) -> tcpip_adapter_if_t {
+    match self {
+      Self::Ap => tcpip_adapter_if_t::TCPIP_ADAPTER_IF_AP,
+      Self::Sta => tcpip_adapter_if_t::TCPIP_ADAPTER_IF_STA,
+    }
+  }
+
+
+  /// Acquire a handle to the network interface of this interface.
+  #[cfg(target_device = "esp8266")]
+  pub fn netif(&self) -> Result<NetIf, EspError> {
+    Ok(NetIf { interface: *self })
+  }
+
+  /// Get the IP information of this interface.
+  ///
+  /// Returns `None` if the interface is not supported, its network interface does
+  /// not currently exist or it has no IP address yet.
+  #[cfg(target_device = "esp32")]
+  pub fn ip_info(&self) -> Option<IpInfo> {
+    let ptr = self.ptr();
+
+    if ptr.is_null() {
+      return None
+    }
+
+    let mut ip_info = MaybeUninit::<ip_info_t>::uninit();
+    esp_ok!(esp_netif_get_ip_info(ptr, ip_info.as_mut_ptr())).ok()?;
+    let mut ip_info = unsafe { IpInfo::from_native_unchecked(ip_info.assume_init()) };
+
+    if ip_info.ip.is_unspecified() {
+      return None
+    }
+
+    ip_info.ipv6 = self.ipv6_addrs();
+    Some(ip_info.with_dns(*self))
+  }
+
+  /// Get the current network interface pointer, which is null if no [`NetIf`](struct.NetIf.html) exists.
+  #[cfg(target_device = "esp32")]
+  fn ptr(&self) -> *mut esp_netif_t {
+    match self {
+      Self::Ap => AP_NETIF.ptr(),
+      Self::Sta => STA_NETIF.ptr(),
+      Self::Eth => crate::eth::ETH_PTR.load(Ordering::SeqCst) as _,
+      _ => ptr::null_mut()
+    }
+  }
+
+  /// Find the interface whose current network interface pointer is `ptr`.
+  #[cfg(target_device = "esp32")]
+  pub(crate) fn from_ptr(ptr: *mut esp_netif_t) -> Option<Self> {
+    if ptr.is_null() {
+      return None
+    }
+
+    [Self::Sta, Self::Ap, Self::Eth].iter().copied().find(|interface| interface.ptr() == ptr)
+  }
+
+  /// Acquire a handle to the network interface of this interface, creating it if necessary.
+  ///
+  /// Only [`Interface::Ap`](#variant.Ap) and [`Interface::Sta`](#variant.Sta) are supported,
+  /// the Ethernet interface is owned by [`Eth`](../eth/struct.Eth.html).
+  #[cfg(target_device = "esp32")]
+  pub fn netif(&self) -> Result<NetIf, EspError> {
+    match self {
+      Self::Ap => AP_NETIF.acquire(|| unsafe { esp_netif_create_default_wifi_ap() })?,
+      Self::Sta => STA_NETIF.acquire(|| unsafe { esp_netif_create_default_wifi_sta() })?,
+      _ => return Err(EspError { code: ESP_ERR_NOT_SUPPORTED as esp_err_t }),
+    }
+
+    Ok(NetIf { interface: *self })
+  }
+}
+
+/// ```no_run
+/// use macaddr::MacAddr6;
+/// use esp32_hal::Interface;
+///
+/// MacAddr6::from(Interface::Ap)
+/// ```
+impl From<Interface> for MacAddr6 {
+  fn from(interface: Interface) -> Self {
+    let mac_address_type = match interface {
+      Interface::Sta => esp_mac_type_t::ESP_MAC_WIFI_STA,
+      Interface::Ap  => esp_mac_type_t::ESP_MAC_WIFI_SOFTAP,
+      #[cfg(target_device = "esp32")]
+      Interface::Bt  => esp_mac_type_t::ESP_MAC_BT,
+      #[cfg(target_device = "esp32")]
+      Interface::Eth => esp_mac_type_t::ESP_MAC_ETH,
+    };
+
+    let mut mac_address = MaybeUninit::<Self>::uninit();
+    esp_ok!(esp_read_mac(mac_address.as_mut_ptr() as *mut _, mac_address_type)).unwrap(); // Can only fail with invalid arguments.
+    unsafe { mac_address.assume_init() }
+  }
+}
+
+/// ```no_run
+/// use macaddr::MacAddr;
+/// use esp32_hal::Interface;
+///
+/// MacAddr::from(Interface::Ap)
+/// ```
+impl From<Interface> for MacAddr {
+  fn from(interface: Interface) -> Self {
+    Self::V6(interface.into())
+  }
+}
+
+/// IP information for an [`Interface`](enum.Interface.html).
+#[derive(Debug, Clone)]
+pub struct IpInfo {
+  ip: Ipv4Addr,
+  netmask: Ipv4Addr,
+  gateway: Ipv4Addr,
+  dns: [Option<IpAddr>; 3],
+  #[cfg(target_device = "esp32")]
+  ipv6: Vec<Ipv6AddrInfo>,
+}
+
+impl IpInfo {
+  pub fn ip(&self) -> &Ipv4Addr {
+    &self.ip
+  }
+
+  pub fn netmask(&self) -> &Ipv4Addr {
+    &self.netmask
+  }
+
+  pub fn gateway(&self) -> &Ipv4Addr {
+    &self.gateway
+  }
+
+  pub fn dns(&self, dns_type: DnsType) -> Option<&IpAddr> {
+    self.dns[dns_type.index()].as_ref()
+  }
+
+  /// IPv6 addresses of the interface at the time this information was retrieved.
+  #[cfg(target_device = "esp32")]
+  pub fn ipv6(&self) -> &[Ipv6AddrInfo] {
+    &self.ipv6
+  }
+}
+
+impl IpInfo {
+  pub(crate) unsafe fn from_native_unchecked(ip_info: ip_info_t) -> Self {
+    IpInfo {
+      ip: u32::from_be(ip_info.ip.addr).into(),
+      netmask: u32::from_be(ip_info.netmask.addr).into(),
+      gateway: u32::from_be(ip_info.gw.addr).into(),
+      dns: [None; 3],
+      #[cfg(target_device = "esp32")]
+      ipv6: Vec::new(),
+    }
+  }
+
+  pub(crate) fn with_dns(mut self, interface: Interface) -> Self {
+    for &dns_type in DnsType::ALL.iter() {
+      self.dns[dns_type.index()] = interface.dns(dns_type).ok().flatten();
+    }
+    self
+  }
+}
@@ -0,0 +1,135 @@
+#[cfg(feature = "napt")]
+use std::net::Ipv4Addr;
+use std::net::SocketAddrV4;
+
+#[cfg(feature = "napt")]
+use esp_idf_bindgen::{ip_napt_enable, ip_portmap_add, ip_portmap_remove, ESP_ERR_INVALID_STATE, ESP_ERR_NO_MEM};
+use esp_idf_bindgen::{esp_err_t, ESP_ERR_NOT_SUPPORTED};
+
+use crate::EspError;
+use super::Interface;
+
+/// Transport protocol of a [`PortMapping`](struct.PortMapping.html).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Protocol {
+  Tcp,
+  Udp,
+}
+
+impl Protocol {
+  #[cfg_attr(not(feature = "napt"), allow(unused))]
+  fn to_native(self) -> u8 {
+    match self {
+      Self::Tcp => 6,
+      Self::Udp => 17,
+    }
+  }
+}
+
+/// A port forwarding rule from the uplink interface to a client of the access point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortMapping {
+  protocol: Protocol,
+  external_port: u16,
+  internal: SocketAddrV4,
+}
+
+impl PortMapping {
+  /// Forward `external_port` on the uplink interface to the `internal` address.
+  pub fn new(protocol: Protocol, external_port: u16, internal: SocketAddrV4) -> Self {
+    Self { protocol, external_port, internal }
+  }
+
+  pub fn protocol(&self) -> Protocol {
+    self.protocol
+  }
+
+  pub fn external_port(&self) -> u16 {
+    self.external_port
+  }
+
+  pub fn internal(&self) -> &SocketAddrV4 {
+    &self.internal
+  }
+}
+
+#[cfg(feature = "napt")]
+fn ip_addr(interface: Interface) -> Result<Ipv4Addr, EspError> {
+  interface.ip_info().map(|ip_info| *ip_info.ip()).ok_or(EspError { code: ESP_ERR_INVALID_STATE as esp_err_t })
+}
+
+impl Interface {
+  /// Enable or disable network address and port translation (NAPT) for clients of this interface.
+  ///
+  /// Only [`Interface::Ap`](#variant.Ap) is supported, its clients are then routed through
+  /// the station uplink. The interface must have an IP address.
+  ///
+  /// This requires the `napt` feature and an ESP-IDF version with `CONFIG_LWIP_IPV4_NAPT`,
+  /// otherwise `ESP_ERR_NOT_SUPPORTED` is returned.
+  pub fn set_napt(&self, enable: bool) -> Result<(), EspError> {
+    if !matches!(self, Self::Ap) || cfg!(not(feature = "napt")) {
+      return Err(EspError { code: ESP_ERR_NOT_SUPPORTED as esp_err_t })
+    }
+
+    #[cfg(feature = "napt")]
+    {
+      let ip = ip_addr(*self)?;
+      unsafe { ip_napt_enable(u32::from(ip).to_be(), enable as _) };
+    }
+
+    #[cfg(not(feature = "napt"))]
+    let _ = enable;
+
+    Ok(())
+  }
+
+  /// Add a port forwarding rule on this uplink interface.
+  ///
+  /// Only [`Interface::Sta`](#variant.Sta) is supported and it must have an IP address.
+  /// NAPT has to be enabled on the access point using [`set_napt`](#method.set_napt).
+  pub fn add_port_mapping(&self, mapping: &PortMapping) -> Result<(), EspError> {
+    if !matches!(self, Self::Sta) || cfg!(not(feature = "napt")) {
+      return Err(EspError { code: ESP_ERR_NOT_SUPPORTED as esp_err_t })
+    }
+
+    #[cfg(feature = "napt")]
+    {
+      let ip = ip_addr(*self)?;
+
+      let added = unsafe {
+        ip_portmap_add(
+          mapping.protocol.to_native(),
+          u32::from(ip).to_be(), mapping.external_port,
+          u32::from(*mapping.internal.ip()).to_be(), mapping.internal.port(),
+        )
+      };
+
+      if added == 0 {
+        return Err(EspError { code: ESP_ERR_NO_MEM as esp_err_t })
+      }
+    }
+
+    #[cfg(not(feature = "napt"))]
+    let _ = mapping;
+
+    Ok(())
+  }
+
+  /// Remove the port forwarding rule for `external_port` from this uplink interface.
+  ///
+  /// Returns whether a rule was removed.
+  pub fn remove_port_mapping(&self, protocol: Protocol, external_port: u16) -> Result<bool, EspError> {
+    if !matches!(self, Self::Sta) || cfg!(not(feature = "napt")) {
+      return Err(EspError { code: ESP_ERR_NOT_SUPPORTED as esp_err_t })
+    }
+
+    #[cfg(feature = "napt")]
+    return Ok(unsafe { ip_portmap_remove(protocol.to_native(), external_port) } != 0);
+
+    #[cfg(not(feature = "napt"))]
+    {
+      let _ = (protocol, external_port);
+      Ok(false)
+    }
+  }
+}
@@ -0,0 +1,224 @@
+#[cfg(feature = "netif-stats")]
+use core::mem::MaybeUninit;
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "netif-stats")]
+use esp_idf_bindgen::{
+  err_enum_t,
+  err_t,
+  esp_netif_get_netif_impl,
+  netif,
+  stats_mib2_netif_ctrs,
+  tcpip_api_call,
+  tcpip_api_call_data,
+};
+
+#[cfg(feature = "netif-stats")]
+use super::Interface;
+
+/// Arguments of `read_counters`, passed through `tcpip_api_call`.
+#[cfg(feature = "netif-stats")]
+#[repr(C)]
+struct CountersCall {
+  call: tcpip_api_call_data,
+  netif: *mut netif,
+  counters: MaybeUninit<stats_mib2_netif_ctrs>,
+}
+
+/// Copy the MIB2 counters of a network interface, called with the lwIP core locked.
+#[cfg(feature = "netif-stats")]
+unsafe extern "C" fn read_counters(call: *mut tcpip_api_call_data) -> err_t {
+  let call = &mut *(call as *mut CountersCall);
+  call.counters = MaybeUninit::new((*call.netif).mib2_counters);
+  err_enum_t::ERR_OK as err_t
+}
+
+#[cfg(feature = "netif-stats")]
+impl Interface {
+  /// Get a snapshot of the traffic statistics of this interface.
+  ///
+  /// The counters are maintained by lwIP since the network interface was created and
+  /// wrap around on overflow. Returns `None` if the interface does not currently exist.
+  ///
+  /// This requires an ESP-IDF version whose lwIP options enable `MIB2_STATS`.
+  pub fn stats(&self) -> Option<Stats> {
+    let esp_netif = self.ptr();
+    if esp_netif.is_null() {
+      return None
+    }
+
+    let netif = unsafe { esp_netif_get_netif_impl(esp_netif) as *mut netif };
+    if netif.is_null() {
+      return None
+    }
+
+    let mut call = CountersCall {
+      call: unsafe { MaybeUninit::zeroed().assume_init() },
+      netif,
+      counters: MaybeUninit::uninit(),
+    };
+
+    if unsafe { tcpip_api_call(Some(read_counters), &mut call.call) } != err_enum_t::ERR_OK as err_t {
+      return None
+    }
+
+    let counters = unsafe { call.counters.assume_init() };
+
+    Some(Stats {
+      instant: Instant::now(),
+      rx_packets: counters.ifinucastpkts.wrapping_add(counters.ifinnucastpkts) as usize,
+      rx_bytes: counters.ifinoctets as usize,
+      rx_dropped: counters.ifindiscards as usize,
+      rx_errors: counters.ifinerrors as usize,
+      tx_packets: counters.ifoutucastpkts.wrapping_add(counters.ifoutnucastpkts) as usize,
+      tx_bytes: counters.ifoutoctets as usize,
+      tx_dropped: counters.ifoutdiscards as usize,
+      tx_errors: counters.ifouterrors as usize,
+    })
+  }
+}
+
+/// A snapshot of the traffic statistics of an [`Interface`](enum.Interface.html).
+#[derive(Debug, Clone, Copy)]
+pub struct Stats {
+  instant: Instant,
+  rx_packets: usize,
+  rx_bytes: usize,
+  rx_dropped: usize,
+  rx_errors: usize,
+  tx_packets: usize,
+  tx_bytes: usize,
+  tx_dropped: usize,
+  tx_errors: usize,
+}
+
+impl Stats {
+  /// The time this snapshot was taken.
+  pub fn instant(&self) -> Instant {
+    self.instant
+  }
+
+  pub fn rx_packets(&self) -> usize {
+    self.rx_packets
+  }
+
+  pub fn rx_bytes(&self) -> usize {
+    self.rx_bytes
+  }
+
+  /// Received packets which were discarded by the network stack.
+  pub fn rx_dropped(&self) -> usize {
+    self.rx_dropped
+  }
+
+  /// Received packets which were malformed.
+  pub fn rx_errors(&self) -> usize {
+    self.rx_errors
+  }
+
+  pub fn tx_packets(&self) -> usize {
+    self.tx_packets
+  }
+
+  pub fn tx_bytes(&self) -> usize {
+    self.tx_bytes
+  }
+
+  /// Packets which were discarded instead of being sent.
+  pub fn tx_dropped(&self) -> usize {
+    self.tx_dropped
+  }
+
+  /// Packets which could not be sent due to errors.
+  pub fn tx_errors(&self) -> usize {
+    self.tx_errors
+  }
+
+  /// Get the difference between this and an `earlier` snapshot.
+  pub fn since(&self, earlier: &Stats) -> StatsDelta {
+    StatsDelta {
+      duration: self.instant.saturating_duration_since(earlier.instant),
+      rx_packets: self.rx_packets.wrapping_sub(earlier.rx_packets),
+      rx_bytes: self.rx_bytes.wrapping_sub(earlier.rx_bytes),
+      rx_dropped: self.rx_dropped.wrapping_sub(earlier.rx_dropped),
+      rx_errors: self.rx_errors.wrapping_sub(earlier.rx_errors),
+      tx_packets: self.tx_packets.wrapping_sub(earlier.tx_packets),
+      tx_bytes: self.tx_bytes.wrapping_sub(earlier.tx_bytes),
+      tx_dropped: self.tx_dropped.wrapping_sub(earlier.tx_dropped),
+      tx_errors: self.tx_errors.wrapping_sub(earlier.tx_errors),
+    }
+  }
+}
+
+/// The difference between two [`Stats`](struct.Stats.html) snapshots.
+#[derive(Debug, Clone, Copy)]
+pub struct StatsDelta {
+  duration: Duration,
+  rx_packets: usize,
+  rx_bytes: usize,
+  rx_dropped: usize,
+  rx_errors: usize,
+  tx_packets: usize,
+  tx_bytes: usize,
+  tx_dropped: usize,
+  tx_errors: usize,
+}
+
+impl StatsDelta {
+  /// The time between the two snapshots.
+  pub fn duration(&self) -> Duration {
+    self.duration
+  }
+
+  pub fn rx_packets(&self) -> usize {
+    self.rx_packets
+  }
+
+  pub fn rx_bytes(&self) -> usize {
+    self.rx_bytes
+  }
+
+  pub fn rx_dropped(&self) -> usize {
+    self.rx_dropped
+  }
+
+  pub fn rx_errors(&self) -> usize {
+    self.rx_errors
+  }
+
+  pub fn tx_packets(&self) -> usize {
+    self.tx_packets
+  }
+
+  pub fn tx_bytes(&self) -> usize {
+    self.tx_bytes
+  }
+
+  pub fn tx_dropped(&self) -> usize {
+    self.tx_dropped
+  }
+
+  pub fn tx_errors(&self) -> usize {
+    self.tx_errors
+  }
+
+  /// Whether any packets were sent or received.
+  pub fn is_active(&self) -> bool {
+    self.rx_packets != 0 || self.tx_packets != 0
+  }
+
+  /// Received bytes per second.
+  pub fn rx_rate(&self) -> f32 {
+    self.rate(self.rx_bytes)
+  }
+
+  /// Sent bytes per second.
+  pub fn tx_rate(&self) -> f32 {
+    self.rate(self.tx_bytes)
+  }
+
+  fn rate(&self, bytes: usize) -> f32 {
+    let secs = self.duration.as_secs_f32();
+    if secs == 0.0 { 0.0 } else { bytes as f32 / secs }
+  }
+}
@@ -12,8 +12,16 @@ extern crate alloc;
 mod esp_error;
 pub use esp_error::EspError;
 
+#[cfg(target_device = "esp32")]
+mod event;
+#[cfg(target_device = "esp32")]
+mod timer;
+mod waker;
+
 pub mod interface;
 mod heap;
 pub use heap::Heap;
 pub mod wifi;
 pub mod nvs;
+#[cfg(target_device = "esp32")]
+pub mod eth;
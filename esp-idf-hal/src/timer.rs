@@ -0,0 +1,170 @@
+use core::fmt;
+use core::future::Future;
+use core::pin::Pin;
+use core::ptr;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use alloc::boxed::Box;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+
+use esp_idf_bindgen::{
+  esp_timer_create,
+  esp_timer_create_args_t,
+  esp_timer_delete,
+  esp_timer_dispatch_t,
+  esp_timer_handle_t,
+  esp_timer_start_once,
+  esp_timer_stop,
+};
+
+use crate::EspError;
+
+type Callback = dyn FnMut() + Send;
+
+/// Callback state owned by the `esp_timer` task once the [`Timer`](struct.Timer.html) is dropped.
+struct TimerCallback {
+  handle: esp_timer_handle_t,
+  dropped: AtomicBool,
+  callback: Box<Callback>,
+}
+
+/// A one-shot timer running its callback on the `esp_timer` task, which is deleted when dropped.
+pub(crate) struct Timer {
+  handle: esp_timer_handle_t,
+  callback: *mut TimerCallback,
+}
+
+unsafe impl Send for Timer {}
+unsafe impl Sync for Timer {}
+
+impl fmt::Debug for Timer {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Timer")
+      .field("handle", &self.handle)
+      .finish()
+  }
+}
+
+impl Timer {
+  /// Create a new timer calling `callback` whenever it expires.
+  pub fn new<F>(callback: F) -> Result<Self, EspError>
+  where
+    F: FnMut() + Send + 'static,
+  {
+    let callback = Box::into_raw(Box::new(TimerCallback {
+      handle: ptr::null_mut(),
+      dropped: AtomicBool::new(false),
+      callback: Box::new(callback),
+    }));
+
+    let args = esp_timer_create_args_t {
+      callback: Some(trampoline),
+      arg: callback as *mut _,
+      dispatch_method: esp_timer_dispatch_t::ESP_TIMER_TASK,
+      name: ptr::null(),
+    };
+
+    let mut handle = ptr::null_mut();
+
+    if let Err(err) = esp_ok!(esp_timer_create(&args, &mut handle)) {
+      drop(unsafe { Box::from_raw(callback) });
+      return Err(err)
+    }
+
+    unsafe { (*callback).handle = handle };
+
+    Ok(Self { handle, callback })
+  }
+
+  /// Start the timer, restarting it if it is already running.
+  pub fn start(&self, timeout: Duration) -> Result<(), EspError> {
+    self.stop();
+    esp_ok!(esp_timer_start_once(self.handle, timeout.as_micros() as u64))
+  }
+
+  /// Stop the timer if it is running.
+  pub fn stop(&self) {
+    let _ = esp_ok!(esp_timer_stop(self.handle));
+  }
+}
+
+impl Drop for Timer {
+  /// Stops the timer and lets the `esp_timer` task delete it, since the callback
+  /// may currently be running on that task.
+  fn drop(&mut self) {
+    self.stop();
+    unsafe { (*self.callback).dropped.store(true, SeqCst) };
+
+    if esp_ok!(esp_timer_start_once(self.handle, 0)).is_err() {
+      // The callback state cannot be freed safely, so it is leaked.
+      let _ = esp_ok!(esp_timer_delete(self.handle));
+    }
+  }
+}
+
+extern "C" fn trampoline(arg: *mut libc::c_void) {
+  let callback = arg as *mut TimerCallback;
+
+  if unsafe { (*callback).dropped.load(SeqCst) } {
+    // Callbacks run one after another on the `esp_timer` task, so no callback is running anymore.
+    let callback = unsafe { Box::from_raw(callback) };
+    let _ = esp_ok!(esp_timer_stop(callback.handle));
+    let _ = esp_ok!(esp_timer_delete(callback.handle));
+    return
+  }
+
+  unsafe { ((*callback).callback)() };
+}
+
+#[derive(Debug, Default)]
+struct DelayState {
+  expired: bool,
+  waker: Option<Waker>,
+}
+
+/// A future which resolves after a given duration.
+#[must_use = "futures do nothing unless polled"]
+#[derive(Debug)]
+pub(crate) struct Delay {
+  state: Arc<Mutex<DelayState>>,
+  timer: Result<Timer, EspError>,
+}
+
+impl Delay {
+  pub fn new(duration: Duration) -> Self {
+    let state = Arc::new(Mutex::new(DelayState::default()));
+
+    let timer_state = Arc::clone(&state);
+    let timer = Timer::new(move || {
+      let mut state = timer_state.lock().unwrap();
+      state.expired = true;
+      if let Some(waker) = state.waker.take() {
+        waker.wake();
+      }
+    });
+
+    let timer = timer.and_then(|timer| timer.start(duration).map(|()| timer));
+
+    Self { state, timer }
+  }
+}
+
+impl Future for Delay {
+  type Output = Result<(), EspError>;
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    if let Err(err) = &self.timer {
+      return Poll::Ready(Err(err.clone()))
+    }
+
+    let mut state = self.state.lock().unwrap();
+
+    if state.expired {
+      Poll::Ready(Ok(()))
+    } else {
+      state.waker = Some(cx.waker().clone());
+      Poll::Pending
+    }
+  }
+}
@@ -0,0 +1,85 @@
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering::{AcqRel, Acquire, Release}};
+use core::task::Waker;
+
+const WAITING: usize = 0;
+const REGISTERING: usize = 0b01;
+const WAKING: usize = 0b10;
+
+/// An owned slot for a [`Waker`](https://doc.rust-lang.org/core/task/struct.Waker.html), which
+/// can be updated by a future and woken from an event handler concurrently.
+pub(crate) struct WakerSlot {
+  state: AtomicUsize,
+  waker: UnsafeCell<Option<Waker>>,
+}
+
+unsafe impl Send for WakerSlot {}
+unsafe impl Sync for WakerSlot {}
+
+impl fmt::Debug for WakerSlot {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("WakerSlot")
+      .field("state", &self.state.load(Acquire))
+      .finish()
+  }
+}
+
+impl Default for WakerSlot {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl WakerSlot {
+  pub const fn new() -> Self {
+    Self { state: AtomicUsize::new(WAITING), waker: UnsafeCell::new(None) }
+  }
+
+  /// Register `waker` to be woken by the next call to [`wake`](#method.wake),
+  /// replacing any previously registered waker.
+  pub fn register(&self, waker: &Waker) {
+    match self.state.compare_exchange(WAITING, REGISTERING, Acquire, Acquire).unwrap_or_else(|state| state) {
+      WAITING => {
+        // Only this thread has access to the waker while in the `REGISTERING` state.
+        let slot = unsafe { &mut *self.waker.get() };
+
+        if !slot.as_ref().map_or(false, |registered| registered.will_wake(waker)) {
+          *slot = Some(waker.clone());
+        }
+
+        if self.state.compare_exchange(REGISTERING, WAITING, AcqRel, Acquire).is_err() {
+          // `wake` was called concurrently, so wake the new waker immediately.
+          let waker = slot.take();
+          self.state.swap(WAITING, AcqRel);
+
+          if let Some(waker) = waker {
+            waker.wake();
+          }
+        }
+      },
+      WAKING => waker.wake_by_ref(),
+      // Registered concurrently from another thread, which is a race in the caller anyway.
+      _ => (),
+    }
+  }
+
+  /// Wake the registered waker, if any.
+  pub fn wake(&self) {
+    if let Some(waker) = self.take() {
+      waker.wake();
+    }
+  }
+
+  fn take(&self) -> Option<Waker> {
+    match self.state.fetch_or(WAKING, AcqRel) {
+      WAITING => {
+        // Only this thread has access to the waker while in the `WAKING` state.
+        let waker = unsafe { (*self.waker.get()).take() };
+        self.state.fetch_and(!WAKING, Release);
+        waker
+      },
+      _ => None,
+    }
+  }
+}
@@ -1,9 +1,65 @@
 use core::fmt;
+use core::mem::MaybeUninit;
+use core::ops::RangeInclusive;
 
-use esp_idf_bindgen::{wifi_config_t, wifi_ap_config_t};
+use esp_idf_bindgen::{esp_wifi_get_country, wifi_config_t, wifi_ap_config_t, wifi_country_t};
+
+use crate::interface::DhcpServerConfig;
 
 use super::{AuthMode, Ssid, Password};
 
+#[cfg(target_device = "esp32")]
+const MAX_CONNECTIONS: u8 = 10;
+#[cfg(target_device = "esp8266")]
+const MAX_CONNECTIONS: u8 = 4;
+
+const MAX_CHANNEL: u8 = 14;
+const BEACON_INTERVAL_RANGE: RangeInclusive<u16> = 100..=60000;
+
+/// Error returned by [`ApConfigBuilder::build`](struct.ApConfigBuilder.html#method.build).
+#[derive(Debug, Clone, Copy)]
+pub enum ApConfigError {
+  /// No SSID was set.
+  MissingSsid,
+  /// The password length is invalid for the authentication mode.
+  InvalidPassword { auth_mode: AuthMode, len: usize },
+  /// The authentication mode is not supported for access points.
+  UnsupportedAuthMode(AuthMode),
+  /// The channel is not between `1` and `14` or not allowed by the current country configuration.
+  InvalidChannel(u8),
+  /// The maximum number of connections is `0` or too large.
+  InvalidMaxConnection(u8),
+  /// The beacon interval is not between `100` and `60000` time units.
+  InvalidBeaconInterval(u16),
+}
+
+impl fmt::Display for ApConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::MissingSsid => "missing SSID".fmt(f),
+      Self::InvalidPassword { auth_mode, len } => write!(f, "password length {} is invalid for {:?}", len, auth_mode),
+      Self::UnsupportedAuthMode(auth_mode) => write!(f, "authentication mode {:?} is not supported for access points", auth_mode),
+      Self::InvalidChannel(channel) => write!(f, "invalid channel {}", channel),
+      Self::InvalidMaxConnection(max_connection) => write!(f, "maximum number of connections must be between 1 and {}, got {}", MAX_CONNECTIONS, max_connection),
+      Self::InvalidBeaconInterval(beacon_interval) => write!(f, "beacon interval must be between {} and {}, got {}", BEACON_INTERVAL_RANGE.start(), BEACON_INTERVAL_RANGE.end(), beacon_interval),
+    }
+  }
+}
+
+/// Whether `channel` is allowed by the current country configuration.
+///
+/// If WiFi is not initialized yet, all channels are allowed.
+fn channel_allowed(channel: u8) -> bool {
+  let mut country = MaybeUninit::<wifi_country_t>::uninit();
+
+  if esp_ok!(esp_wifi_get_country(country.as_mut_ptr())).is_err() {
+    return true
+  }
+
+  let country = unsafe { country.assume_init() };
+  channel >= country.schan && channel < country.schan.saturating_add(country.nchan)
+}
+
 /// Configuration for an access point.
 #[derive(Clone)]
 pub struct ApConfig {
@@ -14,6 +70,7 @@ pub struct ApConfig {
   max_connection: u8,
   ssid_hidden: bool,
   beacon_interval: u16,
+  dhcp_server: Option<DhcpServerConfig>,
 }
 
 impl fmt::Debug for ApConfig {
@@ -26,6 +83,7 @@ impl fmt::Debug for ApConfig {
       .field("max_connection", &self.max_connection)
       .field("ssid_hidden", &self.ssid_hidden)
       .field("beacon_interval", &self.beacon_interval)
+      .field("dhcp_server", &self.dhcp_server)
       .finish()
   }
 }
@@ -39,6 +97,30 @@ impl ApConfig {
     &self.password
   }
 
+  pub fn channel(&self) -> u8 {
+    self.channel
+  }
+
+  pub fn auth_mode(&self) -> AuthMode {
+    self.auth_mode
+  }
+
+  pub fn max_connection(&self) -> u8 {
+    self.max_connection
+  }
+
+  pub fn ssid_hidden(&self) -> bool {
+    self.ssid_hidden
+  }
+
+  pub fn beacon_interval(&self) -> u16 {
+    self.beacon_interval
+  }
+
+  pub fn dhcp_server(&self) -> Option<&DhcpServerConfig> {
+    self.dhcp_server.as_ref()
+  }
+
   pub fn builder() -> ApConfigBuilder {
     ApConfigBuilder::default()
   }
@@ -70,6 +152,7 @@ pub struct ApConfigBuilder {
   max_connection: u8,
   ssid_hidden: bool,
   beacon_interval: u16,
+  dhcp_server: Option<DhcpServerConfig>,
 }
 
 impl fmt::Debug for ApConfigBuilder {
@@ -82,6 +165,7 @@ impl fmt::Debug for ApConfigBuilder {
       .field("max_connection", &self.max_connection)
       .field("ssid_hidden", &self.ssid_hidden)
       .field("beacon_interval", &self.beacon_interval)
+      .field("dhcp_server", &self.dhcp_server)
       .finish()
   }
 }
@@ -91,11 +175,12 @@ impl Default for ApConfigBuilder {
     Self {
       ssid: None,
       password: Default::default(),
-      channel: 0,
+      channel: 1,
       auth_mode: AuthMode::Open,
       max_connection: 4,
       ssid_hidden: false,
       beacon_interval: 100,
+      dhcp_server: None,
     }
   }
 }
@@ -111,15 +196,76 @@ impl ApConfigBuilder {
     self
   }
 
-  pub fn build(&self) -> ApConfig {
-    ApConfig {
-      ssid: self.ssid.clone().expect("missing SSID"),
+  pub fn channel(&mut self, channel: u8) -> &mut Self {
+    self.channel = channel;
+    self
+  }
+
+  pub fn auth_mode(&mut self, auth_mode: AuthMode) -> &mut Self {
+    self.auth_mode = auth_mode;
+    self
+  }
+
+  /// Set the maximum number of connected stations.
+  pub fn max_connection(&mut self, max_connection: u8) -> &mut Self {
+    self.max_connection = max_connection;
+    self
+  }
+
+  pub fn ssid_hidden(&mut self, ssid_hidden: bool) -> &mut Self {
+    self.ssid_hidden = ssid_hidden;
+    self
+  }
+
+  /// Set the beacon interval in time units of 1024 µs.
+  pub fn beacon_interval(&mut self, beacon_interval: u16) -> &mut Self {
+    self.beacon_interval = beacon_interval;
+    self
+  }
+
+  /// Set the IP and DHCP server configuration of the access point.
+  ///
+  /// If unset, the default subnet `192.168.4.0/24` is used.
+  pub fn dhcp_server(&mut self, dhcp_server: DhcpServerConfig) -> &mut Self {
+    self.dhcp_server = Some(dhcp_server);
+    self
+  }
+
+  pub fn build(&self) -> Result<ApConfig, ApConfigError> {
+    let ssid = self.ssid.clone().ok_or(ApConfigError::MissingSsid)?;
+
+    let len = self.password.as_str().len();
+    let valid_password = match self.auth_mode {
+      AuthMode::Open => true,
+      AuthMode::Wep | AuthMode::Wpa2Enterprise | AuthMode::Max => return Err(ApConfigError::UnsupportedAuthMode(self.auth_mode)),
+      _ => (8..=63).contains(&len),
+    };
+
+    if !valid_password {
+      return Err(ApConfigError::InvalidPassword { auth_mode: self.auth_mode, len })
+    }
+
+    if self.channel == 0 || self.channel > MAX_CHANNEL || !channel_allowed(self.channel) {
+      return Err(ApConfigError::InvalidChannel(self.channel))
+    }
+
+    if self.max_connection == 0 || self.max_connection > MAX_CONNECTIONS {
+      return Err(ApConfigError::InvalidMaxConnection(self.max_connection))
+    }
+
+    if !BEACON_INTERVAL_RANGE.contains(&self.beacon_interval) {
+      return Err(ApConfigError::InvalidBeaconInterval(self.beacon_interval))
+    }
+
+    Ok(ApConfig {
+      ssid,
       password: self.password.clone(),
       channel: self.channel,
       auth_mode: self.auth_mode,
       max_connection: self.max_connection,
       ssid_hidden: self.ssid_hidden,
       beacon_interval: self.beacon_interval,
-    }
+      dhcp_server: self.dhcp_server.clone(),
+    })
   }
 }
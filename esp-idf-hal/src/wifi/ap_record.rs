@@ -0,0 +1,248 @@
+use core::fmt;
+use std::str;
+
+use bitflags::bitflags;
+use esp_idf_bindgen::{
+  wifi_ap_record_t,
+  wifi_cipher_type_t,
+  wifi_country_policy_t,
+  wifi_country_t,
+  wifi_second_chan_t,
+};
+use macaddr::MacAddr6;
+
+use super::{AuthMode, Ssid};
+
+/// Position of the secondary channel of a 40 MHz HT channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SecondaryChannel {
+  /// 20 MHz channel without a secondary channel.
+  None,
+  /// The secondary channel is above the primary channel.
+  Above,
+  /// The secondary channel is below the primary channel.
+  Below,
+}
+
+impl From<wifi_second_chan_t> for SecondaryChannel {
+  fn from(second: wifi_second_chan_t) -> Self {
+    match second {
+      wifi_second_chan_t::WIFI_SECOND_CHAN_NONE => Self::None,
+      wifi_second_chan_t::WIFI_SECOND_CHAN_ABOVE => Self::Above,
+      wifi_second_chan_t::WIFI_SECOND_CHAN_BELOW => Self::Below,
+    }
+  }
+}
+
+/// A WiFi cipher type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cipher {
+  None,
+  Wep40,
+  Wep104,
+  Tkip,
+  Ccmp,
+  TkipCcmp,
+  #[cfg(target_device = "esp32")]
+  AesCmac128,
+  Unknown,
+}
+
+impl From<wifi_cipher_type_t> for Cipher {
+  fn from(cipher: wifi_cipher_type_t) -> Self {
+    match cipher {
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_NONE => Self::None,
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_WEP40 => Self::Wep40,
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_WEP104 => Self::Wep104,
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_TKIP => Self::Tkip,
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_CCMP => Self::Ccmp,
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_TKIP_CCMP => Self::TkipCcmp,
+      #[cfg(target_device = "esp32")]
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_AES_CMAC128 => Self::AesCmac128,
+      wifi_cipher_type_t::WIFI_CIPHER_TYPE_UNKNOWN => Self::Unknown,
+    }
+  }
+}
+
+bitflags! {
+  /// Supported PHY modes.
+  pub struct Phy: u8 {
+    /// 802.11b
+    const B = 0b0001;
+    /// 802.11g
+    const G = 0b0010;
+    /// 802.11n
+    const N = 0b0100;
+    /// Espressif long range mode.
+    const LR = 0b1000;
+  }
+}
+
+impl Phy {
+  pub(super) fn from_flags(b: bool, g: bool, n: bool, lr: bool) -> Self {
+    let mut phy = Self::empty();
+    phy.set(Self::B, b);
+    phy.set(Self::G, g);
+    phy.set(Self::N, n);
+    phy.set(Self::LR, lr);
+    phy
+  }
+}
+
+/// Whether country information is taken from the access point or configured manually.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CountryPolicy {
+  Auto,
+  Manual,
+}
+
+/// Country information advertised by an access point.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Country {
+  pub(super) code: [u8; 3],
+  pub(super) first_channel: u8,
+  pub(super) channel_count: u8,
+  pub(super) max_tx_power: i8,
+  pub(super) policy: CountryPolicy,
+}
+
+impl Country {
+  /// The two-letter country code, or an empty string if none is advertised.
+  pub fn code(&self) -> &str {
+    let len = memchr::memchr(0, &self.code[..2]).unwrap_or(2);
+    str::from_utf8(&self.code[..len]).unwrap_or("")
+  }
+
+  /// The first allowed channel.
+  pub fn first_channel(&self) -> u8 {
+    self.first_channel
+  }
+
+  /// The number of allowed channels.
+  pub fn channel_count(&self) -> u8 {
+    self.channel_count
+  }
+
+  /// The maximum transmit power in dBm.
+  pub fn max_tx_power(&self) -> i8 {
+    self.max_tx_power
+  }
+
+  pub fn policy(&self) -> CountryPolicy {
+    self.policy
+  }
+}
+
+impl fmt::Debug for Country {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("Country")
+      .field("code", &self.code())
+      .field("first_channel", &self.first_channel)
+      .field("channel_count", &self.channel_count)
+      .field("max_tx_power", &self.max_tx_power)
+      .field("policy", &self.policy)
+      .finish()
+  }
+}
+
+impl From<&wifi_country_t> for Country {
+  fn from(country: &wifi_country_t) -> Self {
+    Self {
+      code: [country.cc[0] as u8, country.cc[1] as u8, country.cc[2] as u8],
+      first_channel: country.schan,
+      channel_count: country.nchan,
+      max_tx_power: country.max_tx_power,
+      policy: match country.policy {
+        wifi_country_policy_t::WIFI_COUNTRY_POLICY_AUTO => CountryPolicy::Auto,
+        wifi_country_policy_t::WIFI_COUNTRY_POLICY_MANUAL => CountryPolicy::Manual,
+      },
+    }
+  }
+}
+
+/// An access point record returned by a [`ScanFuture`](struct.ScanFuture.html).
+#[derive(Debug, Clone)]
+pub struct ApRecord {
+  pub(super) ssid: Ssid,
+  pub(super) bssid: MacAddr6,
+  pub(super) channel: u8,
+  pub(super) secondary_channel: SecondaryChannel,
+  pub(super) rssi: i8,
+  pub(super) auth_mode: AuthMode,
+  pub(super) pairwise_cipher: Cipher,
+  pub(super) group_cipher: Cipher,
+  pub(super) phy: Phy,
+  pub(super) wps: bool,
+  pub(super) country: Country,
+}
+
+impl ApRecord {
+  pub fn ssid(&self) -> &Ssid {
+    &self.ssid
+  }
+
+  pub fn bssid(&self) -> &MacAddr6 {
+    &self.bssid
+  }
+
+  /// The primary channel.
+  pub fn channel(&self) -> u8 {
+    self.channel
+  }
+
+  pub fn secondary_channel(&self) -> SecondaryChannel {
+    self.secondary_channel
+  }
+
+  /// The signal strength in dBm.
+  pub fn rssi(&self) -> i8 {
+    self.rssi
+  }
+
+  pub fn auth_mode(&self) -> AuthMode {
+    self.auth_mode
+  }
+
+  pub fn pairwise_cipher(&self) -> Cipher {
+    self.pairwise_cipher
+  }
+
+  pub fn group_cipher(&self) -> Cipher {
+    self.group_cipher
+  }
+
+  /// The supported PHY modes.
+  pub fn phy(&self) -> Phy {
+    self.phy
+  }
+
+  /// Whether WiFi Protected Setup is supported.
+  pub fn wps(&self) -> bool {
+    self.wps
+  }
+
+  pub fn country(&self) -> &Country {
+    &self.country
+  }
+}
+
+impl From<&wifi_ap_record_t> for ApRecord {
+  fn from(ap: &wifi_ap_record_t) -> Self {
+    let ssid_len = memchr::memchr(0, &ap.ssid).unwrap_or(ap.ssid.len());
+    let ssid = unsafe { Ssid::from_bytes_unchecked(&ap.ssid[..ssid_len]) };
+
+    Self {
+      ssid,
+      bssid: MacAddr6::from(ap.bssid),
+      channel: ap.primary,
+      secondary_channel: SecondaryChannel::from(ap.second),
+      rssi: ap.rssi,
+      auth_mode: AuthMode::from(ap.authmode),
+      pairwise_cipher: Cipher::from(ap.pairwise_cipher),
+      group_cipher: Cipher::from(ap.group_cipher),
+      phy: Phy::from_flags(ap.phy_11b() != 0, ap.phy_11g() != 0, ap.phy_11n() != 0, ap.phy_lr() != 0),
+      wps: ap.wps() != 0,
+      country: Country::from(&ap.country),
+    }
+  }
+}
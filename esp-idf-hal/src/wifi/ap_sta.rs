@@ -0,0 +1,144 @@
+use esp_idf_bindgen::{esp_interface_t, esp_wifi_connect, esp_wifi_disconnect, esp_wifi_set_config, wifi_config_t};
+
+use crate::EspError;
+use crate::interface::{Interface, IpInfo, NetIf};
+use super::{enter_sta_mode, leave_ap_mode, leave_sta_mode, ApConfig, StaConfig, Wifi, WifiError, WifiRunning};
+
+/// Configuration for running an access point and a station simultaneously.
+#[derive(Debug, Clone)]
+pub struct ApStaConfig {
+  ap: ApConfig,
+  sta: StaConfig,
+}
+
+impl ApStaConfig {
+  pub fn new(ap: ApConfig, sta: StaConfig) -> Self {
+    Self { ap, sta }
+  }
+
+  pub fn ap(&self) -> &ApConfig {
+    &self.ap
+  }
+
+  pub fn sta(&self) -> &StaConfig {
+    &self.sta
+  }
+
+  pub fn into_inner(self) -> (ApConfig, StaConfig) {
+    (self.ap, self.sta)
+  }
+}
+
+/// Configure the station interface and start connecting in the background.
+fn start_sta(config: &StaConfig) -> Result<NetIf, WifiError> {
+  let interface = Interface::Sta;
+  let netif = interface.netif()?;
+  let hostname = config.hostname().map_or_else(|| interface.default_hostname(), ToOwned::to_owned);
+  let mut sta_config = wifi_config_t::from(config);
+
+  enter_sta_mode();
+
+  let result = esp_ok!(esp_wifi_set_config(esp_interface_t::ESP_IF_WIFI_STA, &mut sta_config))
+    .and_then(|()| interface.set_hostname(&hostname))
+    .and_then(|()| esp_ok!(esp_wifi_connect()));
+
+  if let Err(err) = result {
+    leave_sta_mode();
+    return Err(err.into())
+  }
+
+  Ok(netif)
+}
+
+impl Wifi {
+  /// Start an access point and connect to a WiFi network at the same time.
+  ///
+  /// See [`Wifi<ApConfig>::start_sta`](struct.Wifi.html#method.start_sta). If starting
+  /// the station fails, the access point is stopped as well.
+  pub fn start_ap_sta(self, ap_config: ApConfig, sta_config: StaConfig) -> Result<WifiRunning, WifiError> {
+    match self.start_ap(ap_config)? {
+      WifiRunning::Ap(ap) => ap.start_sta(sta_config).map_err(|(err, ap)| {
+        let (_, mut wifi) = ap.stop();
+        wifi.deinit_on_drop = false;
+        err
+      }),
+      _ => unreachable!(),
+    }
+  }
+}
+
+impl Wifi<ApConfig> {
+  /// Connect to a WiFi network using the specified [`StaConfig`](struct.StaConfig.html)
+  /// while keeping the access point running.
+  ///
+  /// The station connects in the background, use [`sta_ip_info`](#method.sta_ip_info)
+  /// or [`events`](#method.events) to find out when it is connected. If starting the
+  /// station fails, the still running access point is returned alongside the error.
+  pub fn start_sta(self, config: StaConfig) -> Result<WifiRunning, (WifiError, Self)> {
+    let (ap_config, ip_info, mut netifs) = self.into_parts();
+
+    match start_sta(&config) {
+      Ok(netif) => {
+        netifs.push(netif);
+        Ok(WifiRunning::ApSta(Wifi::from_parts(ApStaConfig::new(ap_config, config), ip_info, netifs)))
+      },
+      Err(err) => Err((err, Wifi::from_parts(ap_config, ip_info, netifs))),
+    }
+  }
+}
+
+impl Wifi<ApStaConfig> {
+  /// The IP info of the access point.
+  pub fn ip_info(&self) -> Option<&IpInfo> {
+    self.ip_info.as_ref()
+  }
+
+  /// The IP info of the station, if it is connected.
+  pub fn sta_ip_info(&self) -> Option<IpInfo> {
+    Interface::Sta.ip_info()
+  }
+
+  /// Enable or disable routing of access point clients through the station uplink.
+  ///
+  /// See [`Interface::set_napt`](../interface/enum.Interface.html#method.set_napt).
+  pub fn set_napt(&mut self, enable: bool) -> Result<(), EspError> {
+    Interface::Ap.set_napt(enable)
+  }
+
+  /// Stop the access point and keep the station running.
+  ///
+  /// The IP info of the returned station is `None` if it is not connected yet.
+  pub fn stop_ap(self) -> (ApConfig, Wifi<StaConfig>) {
+    let sta_ip_info = self.sta_ip_info();
+
+    let _ = Interface::Ap.set_napt(false);
+    let (config, _, mut netifs) = self.into_parts();
+    leave_ap_mode();
+    netifs.retain(|netif| !matches!(netif.interface(), Interface::Ap));
+
+    let (ap_config, sta_config) = config.into_inner();
+    (ap_config, Wifi::from_parts(sta_config, sta_ip_info, netifs))
+  }
+
+  /// Disconnect and stop the station and keep the access point running.
+  pub fn stop_sta(self) -> (StaConfig, Wifi<ApConfig>) {
+    let (config, ip_info, mut netifs) = self.into_parts();
+    let _ = esp_ok!(esp_wifi_disconnect());
+    leave_sta_mode();
+    netifs.retain(|netif| !matches!(netif.interface(), Interface::Sta));
+
+    let (ap_config, sta_config) = config.into_inner();
+    (sta_config, Wifi::from_parts(ap_config, ip_info, netifs))
+  }
+
+  /// Stop both the access point and the station.
+  pub fn stop(self) -> (ApStaConfig, Wifi) {
+    let _ = Interface::Ap.set_napt(false);
+    let (config, _, netifs) = self.into_parts();
+    let _ = esp_ok!(esp_wifi_disconnect());
+    leave_sta_mode();
+    leave_ap_mode();
+    drop(netifs);
+    (config, Wifi::from_parts((), None, Vec::new()))
+  }
+}
@@ -0,0 +1,269 @@
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use std::collections::VecDeque;
+use std::net::Ipv4Addr;
+use std::sync::{Arc, Mutex};
+
+use esp_idf_bindgen::{
+  esp_event_base_t,
+  ip_event_ap_staipassigned_t,
+  ip_event_got_ip6_t,
+  ip_event_got_ip_t,
+  ip_event_t,
+  wifi_event_ap_staconnected_t,
+  wifi_event_ap_stadisconnected_t,
+  wifi_event_sta_authmode_change_t,
+  wifi_event_sta_connected_t,
+  wifi_event_sta_disconnected_t,
+  wifi_event_sta_scan_done_t,
+  wifi_event_t,
+  ESP_EVENT_ANY_ID,
+  IP_EVENT,
+  WIFI_EVENT,
+};
+use futures_core::Stream;
+use macaddr::MacAddr6;
+
+use crate::EspError;
+use crate::event::EventHandler;
+use crate::interface::{Interface, IpInfo, Ipv6AddrInfo};
+use super::{AuthMode, Ssid};
+
+/// The default number of buffered events per [`WifiEvents`](struct.WifiEvents.html) subscriber.
+pub const DEFAULT_EVENT_CAPACITY: usize = 16;
+
+/// A WiFi or IP event.
+#[derive(Debug, Clone)]
+pub enum WifiEvent {
+  /// The station was started.
+  StaStart,
+  /// The station was stopped.
+  StaStop,
+  /// The station connected to an access point.
+  StaConnected { ssid: Ssid, bssid: MacAddr6, channel: u8, auth_mode: AuthMode },
+  /// The station disconnected from or failed to connect to an access point.
+  ///
+  /// The `reason` is a raw `wifi_err_reason_t` value, which may be unknown to the bindings.
+  StaDisconnected { ssid: Ssid, bssid: MacAddr6, reason: u8 },
+  /// The authentication mode of the access point the station is connected to changed.
+  StaAuthModeChanged { old: AuthMode, new: AuthMode },
+  /// The station obtained an IP address.
+  GotIp { ip_info: IpInfo, changed: bool },
+  /// The station lost its IP address.
+  LostIp,
+  /// An interface obtained an IPv6 address, see [`Ipv6AddrInfo::is_global`](../interface/struct.Ipv6AddrInfo.html#method.is_global).
+  GotIp6 { interface: Interface, addr: Ipv6AddrInfo },
+  /// The access point was started.
+  ApStart,
+  /// The access point was stopped.
+  ApStop,
+  /// A station connected to the access point.
+  ApStaConnected { mac: MacAddr6, aid: u8 },
+  /// A station disconnected from the access point.
+  ApStaDisconnected { mac: MacAddr6, aid: u8 },
+  /// The DHCP server of the access point assigned an IP address to a station.
+  ApStaIpAssigned { ip: Ipv4Addr },
+  /// A scan finished.
+  ScanDone { success: bool, count: u8 },
+}
+
+/// WiFi events converted to a [`WifiEvent`](enum.WifiEvent.html).
+const WIFI_EVENTS: [wifi_event_t; 10] = [
+  wifi_event_t::WIFI_EVENT_STA_START,
+  wifi_event_t::WIFI_EVENT_STA_STOP,
+  wifi_event_t::WIFI_EVENT_STA_CONNECTED,
+  wifi_event_t::WIFI_EVENT_STA_DISCONNECTED,
+  wifi_event_t::WIFI_EVENT_STA_AUTHMODE_CHANGE,
+  wifi_event_t::WIFI_EVENT_AP_START,
+  wifi_event_t::WIFI_EVENT_AP_STOP,
+  wifi_event_t::WIFI_EVENT_AP_STACONNECTED,
+  wifi_event_t::WIFI_EVENT_AP_STADISCONNECTED,
+  wifi_event_t::WIFI_EVENT_SCAN_DONE,
+];
+
+/// IP events converted to a [`WifiEvent`](enum.WifiEvent.html).
+const IP_EVENTS: [ip_event_t; 4] = [
+  ip_event_t::IP_EVENT_STA_GOT_IP,
+  ip_event_t::IP_EVENT_STA_LOST_IP,
+  ip_event_t::IP_EVENT_AP_STAIPASSIGNED,
+  ip_event_t::IP_EVENT_GOT_IP6,
+];
+
+impl WifiEvent {
+  fn from_native(event_base: esp_event_base_t, event_id: i32, event_data: *mut libc::c_void) -> Option<Self> {
+    if event_base == unsafe { WIFI_EVENT } {
+      // Event IDs are not guaranteed to be known to the bindings, so they are not transmuted.
+      let event_id = WIFI_EVENTS.iter().copied().find(|&event| event as i32 == event_id)?;
+
+      Some(match event_id {
+        wifi_event_t::WIFI_EVENT_STA_START => Self::StaStart,
+        wifi_event_t::WIFI_EVENT_STA_STOP => Self::StaStop,
+        wifi_event_t::WIFI_EVENT_STA_CONNECTED => {
+          let event = unsafe { &*(event_data as *const wifi_event_sta_connected_t) };
+
+          Self::StaConnected {
+            ssid: Ssid { ssid: event.ssid, ssid_len: event.ssid_len as usize },
+            bssid: MacAddr6::from(event.bssid),
+            channel: event.channel,
+            auth_mode: AuthMode::from(event.authmode),
+          }
+        },
+        wifi_event_t::WIFI_EVENT_STA_DISCONNECTED => {
+          let event = unsafe { &*(event_data as *const wifi_event_sta_disconnected_t) };
+
+          Self::StaDisconnected {
+            ssid: Ssid { ssid: event.ssid, ssid_len: event.ssid_len as usize },
+            bssid: MacAddr6::from(event.bssid),
+            reason: event.reason,
+          }
+        },
+        wifi_event_t::WIFI_EVENT_STA_AUTHMODE_CHANGE => {
+          let event = unsafe { &*(event_data as *const wifi_event_sta_authmode_change_t) };
+
+          Self::StaAuthModeChanged {
+            old: AuthMode::from(event.old_mode),
+            new: AuthMode::from(event.new_mode),
+          }
+        },
+        wifi_event_t::WIFI_EVENT_AP_START => Self::ApStart,
+        wifi_event_t::WIFI_EVENT_AP_STOP => Self::ApStop,
+        wifi_event_t::WIFI_EVENT_AP_STACONNECTED => {
+          let event = unsafe { &*(event_data as *const wifi_event_ap_staconnected_t) };
+          Self::ApStaConnected { mac: MacAddr6::from(event.mac), aid: event.aid }
+        },
+        wifi_event_t::WIFI_EVENT_AP_STADISCONNECTED => {
+          let event = unsafe { &*(event_data as *const wifi_event_ap_stadisconnected_t) };
+          Self::ApStaDisconnected { mac: MacAddr6::from(event.mac), aid: event.aid }
+        },
+        wifi_event_t::WIFI_EVENT_SCAN_DONE => {
+          let event = unsafe { &*(event_data as *const wifi_event_sta_scan_done_t) };
+          Self::ScanDone { success: event.status == 0, count: event.number }
+        },
+        _ => return None,
+      })
+    } else if event_base == unsafe { IP_EVENT } {
+      let event_id = IP_EVENTS.iter().copied().find(|&event| event as i32 == event_id)?;
+
+      Some(match event_id {
+        ip_event_t::IP_EVENT_STA_GOT_IP => {
+          let event = unsafe { &*(event_data as *const ip_event_got_ip_t) };
+
+          Self::GotIp {
+            ip_info: unsafe { IpInfo::from_native_unchecked(event.ip_info) }.with_dns(Interface::Sta),
+            changed: event.ip_changed,
+          }
+        },
+        ip_event_t::IP_EVENT_STA_LOST_IP => Self::LostIp,
+        ip_event_t::IP_EVENT_GOT_IP6 => {
+          let event = unsafe { &*(event_data as *const ip_event_got_ip6_t) };
+          let (interface, addr) = Ipv6AddrInfo::from_event(event)?;
+          Self::GotIp6 { interface, addr }
+        },
+        ip_event_t::IP_EVENT_AP_STAIPASSIGNED => {
+          let event = unsafe { &*(event_data as *const ip_event_ap_staipassigned_t) };
+          Self::ApStaIpAssigned { ip: Ipv4Addr::from(u32::from_be(event.ip.addr)) }
+        },
+        _ => return None,
+      })
+    } else {
+      None
+    }
+  }
+}
+
+/// A bounded queue of events shared between an event handler and a stream.
+#[derive(Debug)]
+pub(super) struct EventQueue<T> {
+  events: VecDeque<T>,
+  capacity: usize,
+  dropped: usize,
+  closed: bool,
+  waker: Option<Waker>,
+}
+
+impl<T> EventQueue<T> {
+  pub fn new(capacity: usize) -> Self {
+    let capacity = capacity.max(1);
+    Self { events: VecDeque::with_capacity(capacity), capacity, dropped: 0, closed: false, waker: None }
+  }
+
+  /// Push an event, dropping the oldest event if the queue is full.
+  pub fn push(&mut self, event: T) {
+    if self.events.len() >= self.capacity {
+      self.events.pop_front();
+      self.dropped += 1;
+    }
+
+    self.events.push_back(event);
+
+    if let Some(waker) = self.waker.take() {
+      waker.wake();
+    }
+  }
+
+  /// Close the queue, ending the stream once all buffered events are consumed.
+  pub fn close(&mut self) {
+    self.closed = true;
+
+    if let Some(waker) = self.waker.take() {
+      waker.wake();
+    }
+  }
+
+  pub fn poll_pop(&mut self, cx: &mut Context) -> Poll<Option<T>> {
+    match self.events.pop_front() {
+      Some(event) => Poll::Ready(Some(event)),
+      None if self.closed => Poll::Ready(None),
+      None => {
+        self.waker = Some(cx.waker().clone());
+        Poll::Pending
+      },
+    }
+  }
+
+  pub fn dropped(&self) -> usize {
+    self.dropped
+  }
+}
+
+/// A stream of [`WifiEvent`](enum.WifiEvent.html)s.
+///
+/// Every subscriber receives all events independently. If a subscriber falls behind,
+/// the oldest buffered events are dropped.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct WifiEvents {
+  queue: Arc<Mutex<EventQueue<WifiEvent>>>,
+  _wifi_handler: EventHandler,
+  _ip_handler: EventHandler,
+}
+
+impl WifiEvents {
+  pub(crate) fn new(capacity: usize) -> Result<Self, EspError> {
+    let queue = Arc::new(Mutex::new(EventQueue::new(capacity)));
+
+    let handler = |queue: Arc<Mutex<EventQueue<WifiEvent>>>| move |event_base: esp_event_base_t, event_id: i32, event_data: *mut libc::c_void| {
+      if let Some(event) = WifiEvent::from_native(event_base, event_id, event_data) {
+        queue.lock().unwrap().push(event);
+      }
+    };
+
+    let wifi_handler = EventHandler::register(unsafe { WIFI_EVENT }, ESP_EVENT_ANY_ID as _, handler(Arc::clone(&queue)))?;
+    let ip_handler = EventHandler::register(unsafe { IP_EVENT }, ESP_EVENT_ANY_ID as _, handler(Arc::clone(&queue)))?;
+
+    Ok(Self { queue, _wifi_handler: wifi_handler, _ip_handler: ip_handler })
+  }
+
+  /// The number of events dropped so far because the buffer was full.
+  pub fn dropped(&self) -> usize {
+    self.queue.lock().unwrap().dropped()
+  }
+}
+
+impl Stream for WifiEvents {
+  type Item = WifiEvent;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    self.queue.lock().unwrap().poll_pop(cx)
+  }
+}
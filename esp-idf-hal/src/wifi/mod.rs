@@ -1,18 +1,22 @@
-use core::mem::transmute;
 use std::str::{self, FromStr, Utf8Error};
 use std::ops::Deref;
 use std::cmp::{Eq, Ord, Ordering};
 use core::ptr;
-use std::mem::{self, MaybeUninit};
+use std::mem;
+use std::sync::{Arc, Mutex};
 use std::sync::atomic::{AtomicBool, AtomicU8, Ordering::SeqCst};
-use core::task::{Poll, Context, Waker};
+use core::task::{Poll, Context};
+#[cfg(target_device = "esp32")]
+use core::time::Duration;
 use core::pin::Pin;
-use alloc::boxed::Box;
 
 use core::fmt;
 use macaddr::MacAddr6;
 
-use crate::{EspError, nvs::NonVolatileStorage, interface::{Interface, IpInfo}};
+use crate::{EspError, nvs::NonVolatileStorage, interface::{Interface, IpInfo, MacAddrError, NetIf}};
+use crate::waker::WakerSlot;
+#[cfg(target_device = "esp32")]
+use crate::{event::EventHandler, timer::Timer};
 
 use esp_idf_bindgen::*;
 
@@ -22,9 +26,36 @@ pub use sta_config::*;
 mod ap_config;
 pub use ap_config::*;
 
+mod ap_sta;
+pub use ap_sta::*;
+
+mod stations;
+pub use stations::*;
+
+mod ap_record;
+pub use ap_record::*;
+
 mod scan;
 pub use scan::*;
 
+mod scan_results;
+pub use scan_results::*;
+
+#[cfg(target_device = "esp32")]
+mod scan_stream;
+#[cfg(target_device = "esp32")]
+pub use scan_stream::*;
+
+#[cfg(target_device = "esp32")]
+mod event;
+#[cfg(target_device = "esp32")]
+pub use event::*;
+
+#[cfg(target_device = "esp32")]
+mod supervisor;
+#[cfg(target_device = "esp32")]
+pub use supervisor::*;
+
 const SSID_MAX_LEN: usize = 32;
 const PASSWORD_MAX_LEN: usize = 64;
 
@@ -212,7 +243,7 @@ impl fmt::Display for Password {
 }
 
 /// A WiFi authentication mode.
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum AuthMode {
   Open,
   Wep,
@@ -270,15 +301,18 @@ pub struct Wifi<T = ()> {
   config: T,
   deinit_on_drop: bool,
   ip_info: Option<IpInfo>,
+  netifs: Vec<NetIf>,
+  #[cfg(target_device = "esp32")]
+  supervisor: Option<Supervisor>,
 }
 
 #[cfg(target_device = "esp8266")]
-fn initialize_network_interface() {
+pub(crate) fn initialize_network_interface() {
   unsafe { tcpip_adapter_init() };
 }
 
 #[cfg(target_device = "esp32")]
-fn initialize_network_interface() {
+pub(crate) fn initialize_network_interface() {
   static NETIF_STATE: AtomicU8 = AtomicU8::new(0);
 
   loop {
@@ -294,7 +328,7 @@ fn initialize_network_interface() {
   }
 }
 
-fn event_loop_create_default() {
+pub(crate) fn event_loop_create_default() {
   static EVENT_LOOP_STATE: AtomicU8 = AtomicU8::new(0);
 
   loop {
@@ -312,6 +346,34 @@ fn event_loop_create_default() {
 
 static AP_COUNT: AtomicU8 = AtomicU8::new(0);
 static STA_COUNT: AtomicU8 = AtomicU8::new(0);
+/// Number of started [`ConnectFuture`](struct.ConnectFuture.html)s which are still connecting.
+static CONNECTING: AtomicU8 = AtomicU8::new(0);
+
+/// Whether a [`ConnectFuture`](struct.ConnectFuture.html) is currently connecting.
+fn is_connecting() -> bool {
+  CONNECTING.load(SeqCst) > 0
+}
+
+/// Whether WiFi is started by an access point, station or scan.
+fn is_started() -> bool {
+  AP_COUNT.load(SeqCst) > 0 || STA_COUNT.load(SeqCst) > 0
+}
+
+/// Decrement `count` unless it was reset to zero, returning whether it reached zero.
+fn release_mode(count: &AtomicU8) -> bool {
+  let mut current = count.load(SeqCst);
+
+  loop {
+    if current == 0 {
+      return false
+    }
+
+    match count.compare_exchange(current, current - 1, SeqCst, SeqCst) {
+      Ok(_) => return current == 1,
+      Err(actual) => current = actual,
+    }
+  }
+}
 
 fn get_mode() -> Result<wifi_mode_t, EspError> {
   let mut mode = wifi_mode_t::WIFI_MODE_NULL;
@@ -337,7 +399,7 @@ fn enter_ap_mode() {
 }
 
 fn leave_ap_mode() {
-  if AP_COUNT.fetch_sub(1, SeqCst) != 1 {
+  if !release_mode(&AP_COUNT) {
     return
   }
 
@@ -372,7 +434,7 @@ fn enter_sta_mode() {
 }
 
 fn leave_sta_mode() {
-  if STA_COUNT.fetch_sub(1, SeqCst) != 1 {
+  if !release_mode(&STA_COUNT) {
     return
   }
 
@@ -391,6 +453,28 @@ fn leave_sta_mode() {
 
 static WIFI_ACTIVE: AtomicBool = AtomicBool::new(false);
 
+/// Configure and start the access point interface.
+fn start_ap(config: &ApConfig) -> Result<NetIf, WifiError> {
+  let interface = Interface::Ap;
+  let netif = interface.netif()?;
+  if let Some(dhcp_server) = config.dhcp_server() {
+    interface.set_dhcp_server_config(dhcp_server)?;
+  }
+  let mut ap_config = wifi_config_t::from(config);
+
+  enter_ap_mode();
+
+  let result = esp_ok!(esp_wifi_set_config(esp_interface_t::ESP_IF_WIFI_AP, &mut ap_config))
+    .and_then(|()| esp_ok!(esp_wifi_start()));
+
+  if let Err(err) = result {
+    leave_ap_mode();
+    return Err(err.into())
+  }
+
+  Ok(netif)
+}
+
 impl Wifi {
   /// Take the WiFi peripheral if it is not already in use.
   pub fn take() -> Option<Wifi> {
@@ -405,39 +489,52 @@ impl Wifi {
       let config = wifi_init_config_t::default();
       esp_ok!(esp_wifi_init(&config)).expect("failed to initialize WiFi with default configuration");
 
-      Some(Wifi { config: (), deinit_on_drop: true, ip_info: None })
+      Some(Wifi::from_parts((), None, Vec::new()))
     }
   }
 
   /// Start an access point using the specified [`ApConfig`](struct.ApConfig.html).
+  ///
+  /// If starting the access point fails, WiFi stays initialized and can be
+  /// recovered using [`WifiError::wifi`](enum.WifiError.html#method.wifi).
   pub fn start_ap(mut self, config: ApConfig) -> Result<WifiRunning, WifiError> {
+    let result = start_ap(&config);
+
+    // WiFi is now owned by either the running access point or the error.
     self.deinit_on_drop = false;
 
-    let interface = Interface::Ap;
-    interface.init();
-    let mut ap_config = wifi_config_t::from(&config);
-    enter_ap_mode();
-    esp_ok!(esp_wifi_set_config(esp_interface_t::ESP_IF_WIFI_AP, &mut ap_config))?;
-    esp_ok!(esp_wifi_start())?;
-    Ok(WifiRunning::Ap(Wifi { config, deinit_on_drop: true, ip_info: Some(interface.ip_info()) }))
+    let netif = result?;
+    Ok(WifiRunning::Ap(Wifi::from_parts(config, Interface::Ap.ip_info(), vec![netif])))
   }
 
   /// Connect to a WiFi network using the specified [`StaConfig`](struct.StaConfig.html).
   pub fn connect_sta(mut self, config: StaConfig) -> ConnectFuture {
     self.deinit_on_drop = false;
 
-    Interface::Sta.init();
+    let interface = Interface::Sta;
+    let netif = interface.netif();
     let mut sta_config = wifi_config_t::from(&config);
 
     enter_sta_mode();
 
-    let state = if let Err(err) = esp_ok!(esp_wifi_set_config(esp_interface_t::ESP_IF_WIFI_STA, &mut sta_config)) {
-      ConnectFutureState::Failed(err.into())
-    } else {
-      ConnectFutureState::Starting
+    let hostname = config.hostname().map_or_else(|| interface.default_hostname(), ToOwned::to_owned);
+
+    let (state, netif) = match netif {
+      Err(err) => (ConnectFutureState::Failed(err.into()), None),
+      Ok(netif) => {
+        let state = if let Err(err) = esp_ok!(esp_wifi_set_config(esp_interface_t::ESP_IF_WIFI_STA, &mut sta_config)) {
+          ConnectFutureState::Failed(err.into())
+        } else if let Err(err) = interface.set_hostname(&hostname) {
+          ConnectFutureState::Failed(err.into())
+        } else {
+          ConnectFutureState::Starting
+        };
+
+        (state, Some(netif))
+      },
     };
 
-    ConnectFuture { config, state }
+    ConnectFuture::new(config, state, netif)
   }
 }
 
@@ -447,6 +544,7 @@ impl Wifi {
 pub enum WifiRunning {
   Sta(Wifi<StaConfig>),
   Ap(Wifi<ApConfig>),
+  ApSta(Wifi<ApStaConfig>),
 }
 
 impl WifiRunning {
@@ -454,37 +552,105 @@ impl WifiRunning {
     match self {
       Self::Sta(wifi) => wifi.scan(scan_config),
       Self::Ap(wifi) => wifi.scan(scan_config),
+      Self::ApSta(wifi) => wifi.scan(scan_config),
+    }
+  }
+
+  /// Scan nearby WiFi networks periodically, see [`Wifi::scan_stream`](struct.Wifi.html#method.scan_stream).
+  #[cfg(target_device = "esp32")]
+  pub fn scan_stream(&mut self, scan_config: &ScanConfig, interval: Duration) -> Result<ScanStream, EspError> {
+    match self {
+      Self::Sta(wifi) => wifi.scan_stream(scan_config, interval),
+      Self::Ap(wifi) => wifi.scan_stream(scan_config, interval),
+      Self::ApSta(wifi) => wifi.scan_stream(scan_config, interval),
     }
   }
 
-  pub fn ip_info(&self) -> &IpInfo {
+  pub fn ip_info(&self) -> Option<&IpInfo> {
     match self {
       Self::Sta(wifi) => wifi.ip_info(),
       Self::Ap(wifi) => wifi.ip_info(),
+      Self::ApSta(wifi) => wifi.ip_info(),
+    }
+  }
+
+  /// Subscribe to WiFi and IP events.
+  #[cfg(target_device = "esp32")]
+  pub fn events(&self) -> Result<WifiEvents, EspError> {
+    match self {
+      Self::Sta(wifi) => wifi.events(),
+      Self::Ap(wifi) => wifi.events(),
+      Self::ApSta(wifi) => wifi.events(),
     }
   }
 }
 
 impl<T> Wifi<T> {
+  fn from_parts(config: T, ip_info: Option<IpInfo>, netifs: Vec<NetIf>) -> Self {
+    Self {
+      config,
+      deinit_on_drop: true,
+      ip_info,
+      netifs,
+      #[cfg(target_device = "esp32")]
+      supervisor: None,
+    }
+  }
+
+  /// Disassemble this instance without stopping or deinitializing WiFi.
+  fn into_parts(mut self) -> (T, Option<IpInfo>, Vec<NetIf>) {
+    #[cfg(target_device = "esp32")]
+    drop(self.supervisor.take());
+
+    let this = mem::ManuallyDrop::new(self);
+    unsafe { (ptr::read(&this.config), ptr::read(&this.ip_info), ptr::read(&this.netifs)) }
+  }
+
   /// Scan nearby WiFi networks using the specified [`ScanConfig`](struct.ScanConfig.html).
   pub fn scan(&mut self, scan_config: &ScanConfig) -> ScanFuture {
     ScanFuture::new(scan_config)
   }
 
+  /// Scan nearby WiFi networks every `interval` using the specified [`ScanConfig`](struct.ScanConfig.html).
+  #[cfg(target_device = "esp32")]
+  pub fn scan_stream(&mut self, scan_config: &ScanConfig, interval: Duration) -> Result<ScanStream, EspError> {
+    ScanStream::new(scan_config, interval)
+  }
+
   pub fn config(&self) -> &T {
     &self.config
   }
+
+  /// Subscribe to WiFi and IP events, buffering up to
+  /// [`DEFAULT_EVENT_CAPACITY`](constant.DEFAULT_EVENT_CAPACITY.html) events.
+  #[cfg(target_device = "esp32")]
+  pub fn events(&self) -> Result<WifiEvents, EspError> {
+    self.events_with_capacity(DEFAULT_EVENT_CAPACITY)
+  }
+
+  /// Subscribe to WiFi and IP events, buffering up to `capacity` events.
+  #[cfg(target_device = "esp32")]
+  pub fn events_with_capacity(&self, capacity: usize) -> Result<WifiEvents, EspError> {
+    WifiEvents::new(capacity)
+  }
 }
 
 impl<T> Drop for Wifi<T> {
   /// Stops a running WiFi instance and deinitializes it, making it available again
   /// by calling [`Wifi::take()`](struct.Wifi.html#method.take).
   fn drop(&mut self) {
+    #[cfg(target_device = "esp32")]
+    drop(self.supervisor.take());
+
     if self.deinit_on_drop {
-      if mem::size_of::<T>() != 0 {
+      if is_started() {
         unsafe { esp_wifi_stop() };
       }
 
+      // Pending scans or connections no longer hold a mode once WiFi is deinitialized.
+      AP_COUNT.store(0, SeqCst);
+      STA_COUNT.store(0, SeqCst);
+
       let _ = esp_ok!(esp_wifi_deinit());
       NonVolatileStorage::deinit_default();
 
@@ -494,32 +660,38 @@ impl<T> Drop for Wifi<T> {
 }
 
 impl Wifi<StaConfig> {
-  pub fn ip_info(&self) -> &IpInfo {
-    self.ip_info.as_ref().unwrap()
+  pub fn ip_info(&self) -> Option<&IpInfo> {
+    self.ip_info.as_ref()
   }
 
   /// Stop a running WiFi in station mode.
-  pub fn stop(mut self) -> (StaConfig, Wifi) {
-    self.deinit_on_drop = false;
+  pub fn stop(self) -> (StaConfig, Wifi) {
+    let (config, _, netifs) = self.into_parts();
     leave_sta_mode();
-    let config = MaybeUninit::uninit();
-    let config = mem::replace(&mut self.config, unsafe { config.assume_init() });
-    (config, Wifi { config: (), deinit_on_drop: true, ip_info: None })
+    drop(netifs);
+    (config, Wifi::from_parts((), None, Vec::new()))
   }
 }
 
 impl Wifi<ApConfig> {
-  pub fn ip_info(&self) -> &IpInfo {
-    self.ip_info.as_ref().unwrap()
+  pub fn ip_info(&self) -> Option<&IpInfo> {
+    self.ip_info.as_ref()
+  }
+
+  /// Enable or disable routing of access point clients through the station uplink.
+  ///
+  /// See [`Interface::set_napt`](../interface/enum.Interface.html#method.set_napt).
+  pub fn set_napt(&mut self, enable: bool) -> Result<(), EspError> {
+    Interface::Ap.set_napt(enable)
   }
 
   /// Stop a running WiFi access point.
-  pub fn stop(mut self) -> (ApConfig, Wifi) {
-    self.deinit_on_drop = false;
+  pub fn stop(self) -> (ApConfig, Wifi) {
+    let _ = Interface::Ap.set_napt(false);
+    let (config, _, netifs) = self.into_parts();
     leave_ap_mode();
-    let config = MaybeUninit::uninit();
-    let config = mem::replace(&mut self.config, unsafe { config.assume_init() });
-    (config, Wifi { config: (), deinit_on_drop: true, ip_info: None })
+    drop(netifs);
+    (config, Wifi::from_parts((), None, Vec::new()))
   }
 }
 
@@ -531,12 +703,82 @@ enum ConnectFutureState {
   Connected { ip_info: IpInfo, ssid: Ssid, bssid: MacAddr6, channel: u8, auth_mode: AuthMode },
 }
 
+/// State shared between a [`ConnectFuture`](struct.ConnectFuture.html) and its event handlers.
+#[derive(Debug)]
+struct ConnectFutureShared {
+  state: Mutex<ConnectFutureState>,
+  waker: WakerSlot,
+}
+
+impl ConnectFutureShared {
+  /// Transition to the state returned by `f`, if any, and wake the future.
+  fn transition(&self, f: impl FnOnce(&ConnectFutureState) -> Option<ConnectFutureState>) -> bool {
+    let changed = {
+      let mut state = self.state.lock().unwrap();
+
+      match f(&state) {
+        Some(new_state) => {
+          *state = new_state;
+          true
+        },
+        None => false,
+      }
+    };
+
+    if changed {
+      self.waker.wake();
+    }
+
+    changed
+  }
+
+  /// Fail with `error`, unless already failed.
+  fn fail(&self, error: WifiError) {
+    self.transition(|state| match state {
+      ConnectFutureState::Failed(..) => None,
+      _ => Some(ConnectFutureState::Failed(error)),
+    });
+  }
+
+  /// Fail with a timeout in the current phase, unless already connected or failed.
+  #[cfg(target_device = "esp32")]
+  fn time_out(&self) {
+    self.transition(|state| {
+      let phase = match state {
+        ConnectFutureState::Starting => ConnectPhase::Association,
+        ConnectFutureState::ConnectedWithoutIp { .. } => ConnectPhase::Dhcp,
+        _ => return None,
+      };
+
+      Some(ConnectFutureState::Failed(WifiError::Timeout(phase)))
+    });
+  }
+}
+
 /// A future representing an ongoing connection to an access point.
+///
+/// Dropping a pending `ConnectFuture` aborts the connection attempt.
 #[must_use = "futures do nothing unless polled"]
 #[derive(Debug)]
 pub struct ConnectFuture {
-  config: StaConfig,
-  state: ConnectFutureState,
+  config: Option<StaConfig>,
+  shared: Arc<ConnectFutureShared>,
+  netif: Option<NetIf>,
+  #[cfg(target_device = "esp32")]
+  handlers: Vec<EventHandler>,
+  #[cfg(target_device = "esp32")]
+  timers: Vec<Arc<Timer>>,
+  started: bool,
+  finished: bool,
+}
+
+/// Phase of connecting to an access point.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectPhase {
+  /// Associating with the access point.
+  Association,
+  /// Obtaining an IP address using DHCP.
+  Dhcp,
 }
 
 /// The error type returned when a [`ConnectFuture`](struct.ConnectFuture.html) fails.
@@ -544,12 +786,13 @@ pub struct ConnectFuture {
 pub struct ConnectionError {
   ssid: Ssid,
   bssid: MacAddr6,
-  reason: wifi_err_reason_t,
+  /// A raw `wifi_err_reason_t` value, which may be unknown to the bindings.
+  reason: u8,
 }
 
 impl fmt::Display for ConnectionError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-    write!(f, "Error connecting to {} ({}): {:?}", self.ssid, self.bssid, self.reason)
+    write!(f, "Error connecting to {} ({}): reason {}", self.ssid, self.bssid, self.reason)
   }
 }
 
@@ -560,12 +803,16 @@ pub enum WifiError {
   Internal(EspError),
   /// A connection error returned when a [`ConnectFuture`](struct.ConnectFuture.html) fails.
   ConnectionError(ConnectionError),
+  /// An invalid MAC address.
+  MacAddrError(MacAddrError),
+  /// A timeout configured in [`StaConfig`](struct.StaConfig.html) elapsed while connecting.
+  Timeout(ConnectPhase),
 }
 
 impl WifiError {
   /// Create a new uninitialized [`Wifi`](struct.Wifi.html) instance.
   pub fn wifi(self) -> Wifi {
-    Wifi { config: (), deinit_on_drop: true, ip_info: None }
+    Wifi::from_parts((), None, Vec::new())
   }
 }
 
@@ -575,176 +822,222 @@ impl From<EspError> for WifiError {
   }
 }
 
+impl From<MacAddrError> for WifiError {
+  fn from(mac_addr_error: MacAddrError) -> Self {
+    match mac_addr_error {
+      MacAddrError::Internal(esp_error) => Self::Internal(esp_error),
+      err => Self::MacAddrError(err),
+    }
+  }
+}
+
 impl fmt::Display for WifiError {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     match self {
       Self::Internal(esp_error) => esp_error.fmt(f),
       Self::ConnectionError(error) => error.fmt(f),
+      Self::MacAddrError(error) => error.fmt(f),
+      Self::Timeout(ConnectPhase::Association) => "timed out associating with access point".fmt(f),
+      Self::Timeout(ConnectPhase::Dhcp) => "timed out obtaining IP address".fmt(f),
     }
   }
 }
 
-impl core::future::Future for ConnectFuture {
-  type Output = Result<WifiRunning, WifiError>;
-
-  #[cfg(target_device = "esp8266")]
-  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-    Poll::Pending
+impl ConnectFuture {
+  fn new(config: StaConfig, state: ConnectFutureState, netif: Option<NetIf>) -> Self {
+    Self {
+      config: Some(config),
+      shared: Arc::new(ConnectFutureShared { state: Mutex::new(state), waker: WakerSlot::new() }),
+      netif,
+      #[cfg(target_device = "esp32")]
+      handlers: Vec::new(),
+      #[cfg(target_device = "esp32")]
+      timers: Vec::new(),
+      started: false,
+      finished: false,
+    }
   }
 
+  /// Register event handlers and timers and start connecting.
   #[cfg(target_device = "esp32")]
-  fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-    fn register_sta_handlers(b: *mut (Pin<&mut ConnectFuture>, &Waker)) -> Result<(), EspError> {
-      esp_ok!(esp_event_handler_register(
-        WIFI_EVENT, wifi_event_t::WIFI_EVENT_STA_START as _, Some(wifi_sta_handler), b as *mut _,
-      ))?;
-      esp_ok!(esp_event_handler_register(
-        WIFI_EVENT, wifi_event_t::WIFI_EVENT_STA_CONNECTED as _, Some(wifi_sta_handler), b as *mut _,
-      ))?;
-      esp_ok!(esp_event_handler_register(
-        WIFI_EVENT, wifi_event_t::WIFI_EVENT_STA_DISCONNECTED as _, Some(wifi_sta_handler), b as *mut _,
-      ))?;
-      esp_ok!(esp_event_handler_register(
-        IP_EVENT, ip_event_t::IP_EVENT_STA_GOT_IP as _, Some(wifi_sta_handler), b as *mut _,
-      ))
-    }
-
-    fn unregister_sta_handlers() -> Result<(), EspError> {
-      esp_ok!(esp_event_handler_unregister(
-        WIFI_EVENT, wifi_event_t::WIFI_EVENT_STA_START as _, Some(wifi_sta_handler),
-      )).and(esp_ok!(esp_event_handler_unregister(
-        WIFI_EVENT, wifi_event_t::WIFI_EVENT_STA_CONNECTED as _, Some(wifi_sta_handler),
-      ))).and(esp_ok!(esp_event_handler_unregister(
-        WIFI_EVENT, wifi_event_t::WIFI_EVENT_STA_DISCONNECTED as _, Some(wifi_sta_handler),
-      ))).and(esp_ok!(esp_event_handler_unregister(
-        IP_EVENT, ip_event_t::IP_EVENT_STA_GOT_IP as _, Some(wifi_sta_handler),
-      )))
-    }
-
-    match &self.state {
-      ConnectFutureState::Starting => {
-        let b: Box<(Pin<&mut ConnectFuture>, &Waker)> = Box::new((self.as_mut(), cx.waker()));
-        let b = Box::into_raw(b);
-
-        if let Err(err) = register_sta_handlers(b) {
-          let _ = unregister_sta_handlers();
-          drop(unsafe { Box::from_raw(b) });
-          return Poll::Ready(Err(err.into()));
-        }
+  fn start(&mut self) -> Result<(), EspError> {
+    let config = self.config.as_ref().unwrap();
+    let dhcp_timeout = config.dhcp_timeout();
 
-        esp_ok!(esp_wifi_start())?;
-        Poll::Pending
-      },
-      ConnectFutureState::ConnectedWithoutIp { .. } => {
-        Poll::Pending
-      }
-      _ => {
-        if let Err(err) = unregister_sta_handlers() {
-          if !matches!(self.state, ConnectFutureState::Failed(..)) {
-            self.state = ConnectFutureState::Failed(err.into())
-          }
-        }
+    let timeout_callback = |shared: Arc<ConnectFutureShared>| move || shared.time_out();
 
-        match self.state {
-          ConnectFutureState::Starting | ConnectFutureState::ConnectedWithoutIp { .. } => unreachable!(),
-          ConnectFutureState::Failed(ref err) => {
-            leave_sta_mode();
-            Poll::Ready(Err(err.clone().into()))
-          },
-          ConnectFutureState::Connected { ref mut ip_info, .. } => {
-            let ip_info = mem::replace(ip_info, unsafe { MaybeUninit::uninit().assume_init() });
-            let config = mem::replace(&mut self.as_mut().config, unsafe { MaybeUninit::uninit().assume_init() });
-            Poll::Ready(Ok(WifiRunning::Sta(Wifi { config, deinit_on_drop: true, ip_info: Some(ip_info) })))
-          }
-        }
-      }
+    let timer = Arc::new(Timer::new(timeout_callback(Arc::clone(&self.shared)))?);
+    if let Some(timeout) = config.timeout() {
+      timer.start(timeout)?;
     }
-  }
-}
+    self.timers.push(timer);
 
-#[cfg(target_device = "esp32")]
-extern "C" fn wifi_sta_handler(
-  event_handler_arg: *mut libc::c_void,
-  event_base: esp_event_base_t,
-  event_id: i32,
-  event_data: *mut libc::c_void,
-) {
-  if event_base == unsafe { WIFI_EVENT } {
-    let event_id: wifi_event_t = unsafe { transmute(event_id) };
-
-    eprintln!("WIFI_EVENT: {:?}", event_id);
-
-    match event_id {
-      wifi_event_t::WIFI_EVENT_STA_START => {
+    let phase_timer = Arc::new(Timer::new(timeout_callback(Arc::clone(&self.shared)))?);
+    if let Some(timeout) = config.association_timeout() {
+      phase_timer.start(timeout)?;
+    }
+    self.timers.push(Arc::clone(&phase_timer));
+
+    let shared = Arc::clone(&self.shared);
+    self.handlers.push(EventHandler::register(
+      unsafe { WIFI_EVENT }, wifi_event_t::WIFI_EVENT_STA_START as _,
+      move |_, _, _| {
         if let Err(err) = esp_ok!(esp_wifi_connect()) {
-          let (mut f, waker) = unsafe { *Box::from_raw(event_handler_arg as *mut (Pin<&mut ConnectFuture>, &Waker)) };
-          f.state = ConnectFutureState::Failed(err.into());
-          waker.wake_by_ref();
+          shared.fail(err.into());
         }
       },
-      wifi_event_t::WIFI_EVENT_STA_CONNECTED => {
-        let event = unsafe { &*(event_data as *const wifi_event_sta_connected_t) };
+    )?);
 
-        eprintln!("EVENT_DATA: {:?}", event);
+    let shared = Arc::clone(&self.shared);
+    self.handlers.push(EventHandler::register(
+      unsafe { WIFI_EVENT }, wifi_event_t::WIFI_EVENT_STA_CONNECTED as _,
+      move |_, _, event_data| {
+        let event = unsafe { &*(event_data as *const wifi_event_sta_connected_t) };
 
         let ssid = Ssid { ssid: event.ssid, ssid_len: event.ssid_len as usize };
         let bssid = MacAddr6::from(event.bssid);
         let channel = event.channel;
         let auth_mode = AuthMode::from(event.authmode);
 
-        let (ref mut f, _) = unsafe { &mut *(event_handler_arg as *mut (Pin<&mut ConnectFuture>, &Waker)) };
-        f.state = ConnectFutureState::ConnectedWithoutIp { ssid, bssid, channel, auth_mode };
-
-        eprintln!("EVENT_STATE: {:?}", f.state);
+        let associated = shared.transition(|state| match state {
+          ConnectFutureState::Starting => Some(ConnectFutureState::ConnectedWithoutIp { ssid, bssid, channel, auth_mode }),
+          _ => None,
+        });
+
+        if associated {
+          match dhcp_timeout {
+            Some(timeout) => if let Err(err) = phase_timer.start(timeout) {
+              shared.fail(err.into());
+            },
+            None => phase_timer.stop(),
+          }
+        }
       },
-      wifi_event_t::WIFI_EVENT_STA_DISCONNECTED => {
-        let event = unsafe { &*(event_data as *const wifi_event_sta_disconnected_t) };
+    )?);
 
-        eprintln!("EVENT_DATA: {:?}", event);
+    let shared = Arc::clone(&self.shared);
+    self.handlers.push(EventHandler::register(
+      unsafe { WIFI_EVENT }, wifi_event_t::WIFI_EVENT_STA_DISCONNECTED as _,
+      move |_, _, event_data| {
+        let event = unsafe { &*(event_data as *const wifi_event_sta_disconnected_t) };
 
         let ssid = Ssid { ssid: event.ssid, ssid_len: event.ssid_len as usize };
         let bssid = MacAddr6::from(event.bssid);
-        let reason: wifi_err_reason_t = unsafe { transmute(event.reason as u32) };
+        let reason = event.reason;
 
-        let error = ConnectionError {
-          ssid, bssid, reason
-        };
+        shared.fail(WifiError::ConnectionError(ConnectionError { ssid, bssid, reason }));
+      },
+    )?);
 
-        let (mut f, waker) = unsafe { *Box::from_raw(event_handler_arg as *mut (Pin<&mut ConnectFuture>, &Waker)) };
-        f.state = ConnectFutureState::Failed(WifiError::ConnectionError(error));
+    let shared = Arc::clone(&self.shared);
+    self.handlers.push(EventHandler::register(
+      unsafe { IP_EVENT }, ip_event_t::IP_EVENT_STA_GOT_IP as _,
+      move |_, _, event_data| {
+        let event = unsafe { &*(event_data as *const ip_event_got_ip_t) };
 
-        eprintln!("EVENT_STATE: {:?}", f.state);
+        let ip_info = unsafe { IpInfo::from_native_unchecked(event.ip_info) }.with_dns(Interface::Sta);
 
-        waker.wake_by_ref();
+        shared.transition(|state| match state {
+          ConnectFutureState::ConnectedWithoutIp { ssid, bssid, channel, auth_mode } => Some(ConnectFutureState::Connected {
+            ip_info, ssid: ssid.clone(), bssid: *bssid, channel: *channel, auth_mode: *auth_mode,
+          }),
+          _ => None,
+        });
       },
-      _ => (),
+    )?);
+
+    esp_ok!(esp_wifi_start())
+  }
+
+  /// Unregister event handlers and stop timers.
+  fn release(&mut self) {
+    #[cfg(target_device = "esp32")]
+    {
+      self.handlers.clear();
+      self.timers.clear();
     }
-  } else if event_base == unsafe { IP_EVENT } {
-    let event_id: ip_event_t = unsafe { transmute(event_id) };
 
-    eprintln!("IP_EVENT: {:?}", event_id);
+    if self.started && !self.finished {
+      CONNECTING.fetch_sub(1, SeqCst);
+    }
 
-    match event_id {
-      ip_event_t::IP_EVENT_STA_GOT_IP => {
-        let event = unsafe { &*(event_data as *const ip_event_got_ip_t) };
+    self.finished = true;
+  }
 
-        let ip_info = unsafe { IpInfo::from_native_unchecked(event.ip_info) };
+  /// Abort connecting and leave station mode.
+  fn abort(&mut self) {
+    self.release();
 
-        eprintln!("EVENT_DATA: {:?}", event);
+    if self.started {
+      let _ = esp_ok!(esp_wifi_disconnect());
+    }
 
-        let (mut f, waker) = unsafe { *Box::from_raw(event_handler_arg as *mut (Pin<&mut ConnectFuture>, &Waker)) };
+    leave_sta_mode();
+  }
+}
 
-        if let ConnectFutureState::ConnectedWithoutIp { ssid, bssid, channel, auth_mode } = mem::replace(&mut f.state, ConnectFutureState::Starting) {
-          f.state = ConnectFutureState::Connected { ip_info, ssid, bssid, channel, auth_mode };
-        } else {
-          unreachable!();
+impl Drop for ConnectFuture {
+  fn drop(&mut self) {
+    if !self.finished {
+      self.abort();
+    }
+  }
+}
+
+impl core::future::Future for ConnectFuture {
+  type Output = Result<WifiRunning, WifiError>;
+
+  #[cfg(target_device = "esp8266")]
+  fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    Poll::Pending
+  }
+
+  #[cfg(target_device = "esp32")]
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+    let this = &mut *self;
+
+    if this.finished {
+      panic!("`ConnectFuture` polled after completion");
+    }
+
+    if !this.started {
+      let failed = matches!(*this.shared.state.lock().unwrap(), ConnectFutureState::Failed(..));
+
+      if !failed {
+        this.started = true;
+        CONNECTING.fetch_add(1, SeqCst);
+        scan_stream::wake_scan_streams();
+
+        if let Err(err) = this.start() {
+          this.shared.fail(err.into());
         }
+      }
+    }
 
-        eprintln!("EVENT_STATE: {:?}", f.state);
+    // Register before checking the state, so a concurrent transition is not missed.
+    this.shared.waker.register(cx.waker());
 
-        waker.wake_by_ref();
+    let state = {
+      let mut state = this.shared.state.lock().unwrap();
+
+      match *state {
+        ConnectFutureState::Starting | ConnectFutureState::ConnectedWithoutIp { .. } => return Poll::Pending,
+        _ => mem::replace(&mut *state, ConnectFutureState::Starting),
+      }
+    };
+
+    match state {
+      ConnectFutureState::Failed(err) => {
+        this.abort();
+        Poll::Ready(Err(err))
+      },
+      ConnectFutureState::Connected { ip_info, .. } => {
+        this.release();
+        let config = this.config.take().unwrap();
+        let netif = this.netif.take();
+        Poll::Ready(Ok(WifiRunning::Sta(Wifi::from_parts(config, Some(ip_info), netif.into_iter().collect()))))
       },
-      _ => (),
+      ConnectFutureState::Starting | ConnectFutureState::ConnectedWithoutIp { .. } => unreachable!(),
     }
   }
 }
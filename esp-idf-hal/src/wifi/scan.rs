@@ -4,20 +4,36 @@ use core::mem::{self, MaybeUninit};
 use core::pin::Pin;
 use core::ptr;
 use core::task::{Poll, Context, Waker};
-use std::time::Duration;
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex, Once};
+use std::sync::atomic::{AtomicBool, Ordering::SeqCst};
+use std::time::{Duration, Instant};
 
 use esp_idf_bindgen::{
+  esp_err_t,
+  esp_wifi_get_country,
   esp_wifi_scan_start,
+  esp_wifi_scan_stop,
   esp_wifi_scan_get_ap_num,
   esp_wifi_scan_get_ap_records,
   wifi_ap_record_t,
+  wifi_country_t,
   wifi_scan_config_t,
   wifi_scan_time_t,
   wifi_active_scan_time_t,
   wifi_scan_type_t,
+  ESP_ERR_INVALID_ARG,
 };
 use macaddr::MacAddr6;
 
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{wifi_event_t, WIFI_EVENT};
+
+#[cfg(target_device = "esp32")]
+use crate::{event::EventHandler, timer::Delay};
+use crate::interface::random_mac;
+use crate::waker::WakerSlot;
+
 use super::*;
 
 /// Scan type used for scanning nearby WiFi networks.
@@ -25,7 +41,7 @@ use super::*;
 /// For an explanation of the two types, refer to https://www.wi-fi.org/knowledge-center/faq/what-are-passive-and-active-scanning.
 ///
 /// All durations must be between `1` and `u32::max_value()` milliseconds. A duration of `0` means that the default duration will be used.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum ScanType {
   /// Active scanning with a minimum duration of `min` and a maximum duration of `max` per channel.
   Active { min: Duration, max: Duration },
@@ -39,24 +55,133 @@ impl Default for ScanType {
   }
 }
 
+/// Error returned by [`ScanConfigBuilder::build`](struct.ScanConfigBuilder.html#method.build).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScanConfigError {
+  /// A channel is not between `1` and `14`.
+  InvalidChannel(u8),
+  /// The minimum active scan duration is longer than the maximum duration.
+  InvalidActiveScanTime { min: Duration, max: Duration },
+  /// A duration is longer than `u32::max_value()` milliseconds.
+  DurationTooLong(Duration),
+}
+
+impl fmt::Display for ScanConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::InvalidChannel(channel) => write!(f, "invalid channel {}", channel),
+      Self::InvalidActiveScanTime { min, max } => write!(f, "minimum scan time {:?} is longer than maximum scan time {:?}", min, max),
+      Self::DurationTooLong(duration) => write!(f, "duration {:?} is too long", duration),
+    }
+  }
+}
+
+const MAX_CHANNEL: u8 = 14;
+
 /// Configuration used for scanning nearby WiFi networks.
 #[derive(Default, Debug, Clone)]
 pub struct ScanConfig {
   ssid: Option<Ssid>,
   bssid: Option<MacAddr6>,
-  channel: u8,
+  channels: Vec<u8>,
   show_hidden: bool,
   scan_type: ScanType,
+  home_channel_dwell: Option<Duration>,
+  randomize_mac: bool,
+  max_age: Option<Duration>,
 }
 
 impl ScanConfig {
+  /// Whether scanning with `other` yields the same results as scanning with this configuration.
+  fn same_scan(&self, other: &ScanConfig) -> bool {
+    self.ssid == other.ssid &&
+      self.bssid == other.bssid &&
+      self.channels == other.channels &&
+      self.show_hidden == other.show_hidden &&
+      self.scan_type == other.scan_type &&
+      self.home_channel_dwell == other.home_channel_dwell &&
+      self.randomize_mac == other.randomize_mac
+  }
+
+  /// The channels to scan one after another, or an empty list to scan all channels at once.
+  ///
+  /// Channels not allowed by the current country configuration are skipped.
+  fn scan_channels(&self) -> Result<VecDeque<u8>, EspError> {
+    if self.channels.is_empty() && self.home_channel_dwell.is_none() {
+      return Ok(VecDeque::new())
+    }
+
+    let mut country = MaybeUninit::<wifi_country_t>::uninit();
+    esp_ok!(esp_wifi_get_country(country.as_mut_ptr()))?;
+    let country = unsafe { country.assume_init() };
+    let allowed = country.schan..country.schan.saturating_add(country.nchan);
+
+    let channels: VecDeque<u8> = if self.channels.is_empty() {
+      allowed.collect()
+    } else {
+      self.channels.iter().copied().filter(|channel| allowed.contains(channel)).collect()
+    };
+
+    if channels.is_empty() {
+      return Err(EspError { code: ESP_ERR_INVALID_ARG as esp_err_t })
+    }
+
+    Ok(channels)
+  }
+
+  fn to_native(&self, channel: u8) -> wifi_scan_config_t {
+    let duration_as_millis_rounded = |dur: Duration| {
+      let nanos = dur.as_nanos();
+
+      if nanos == 0 {
+        0
+      } else {
+        cmp::min(u32::max_value() as u128, cmp::max(1_000_000, nanos) / 1_000_000) as u32
+      }
+    };
+
+    let (scan_type, scan_time) = match self.scan_type {
+      ScanType::Active { min, max } => (
+        wifi_scan_type_t::WIFI_SCAN_TYPE_ACTIVE,
+        wifi_scan_time_t {
+          active: wifi_active_scan_time_t {
+            min: duration_as_millis_rounded(min),
+            max: duration_as_millis_rounded(max),
+          },
+          #[cfg(target_device = "esp32")]
+          passive: 0,
+        },
+      ),
+      ScanType::Passive { max } => (
+        wifi_scan_type_t::WIFI_SCAN_TYPE_PASSIVE,
+        wifi_scan_time_t {
+          #[cfg(target_device = "esp32")]
+          active: wifi_active_scan_time_t { min: 0, max: 0 },
+          passive: duration_as_millis_rounded(max),
+        },
+      )
+    };
+
+    wifi_scan_config_t {
+      ssid: self.ssid.as_ref().map_or_else(ptr::null_mut, |ssid| ssid.ssid.as_ptr() as *mut _),
+      bssid: self.bssid.as_ref().map_or_else(ptr::null_mut, |bssid| bssid as *const _ as *mut _),
+      channel,
+      show_hidden: self.show_hidden,
+      scan_type,
+      scan_time,
+    }
+  }
+
   pub fn builder() -> ScanConfigBuilder {
     ScanConfigBuilder {
       ssid: None,
       bssid: None,
-      channel: 0,
+      channels: Vec::new(),
       show_hidden: false,
       scan_type: Default::default(),
+      home_channel_dwell: None,
+      randomize_mac: false,
+      max_age: None,
     }
   }
 }
@@ -66,9 +191,12 @@ impl ScanConfig {
 pub struct ScanConfigBuilder {
   ssid: Option<Ssid>,
   bssid: Option<MacAddr6>,
-  channel: u8,
+  channels: Vec<u8>,
   show_hidden: bool,
   scan_type: ScanType,
+  home_channel_dwell: Option<Duration>,
+  randomize_mac: bool,
+  max_age: Option<Duration>,
 }
 
 impl ScanConfigBuilder {
@@ -82,9 +210,15 @@ impl ScanConfigBuilder {
     self
   }
 
-
+  /// Scan only `channel`, or all channels if `channel` is `0`.
   pub fn channel(mut self, channel: u8) -> ScanConfigBuilder {
-    self.channel = channel;
+    self.channels = if channel == 0 { Vec::new() } else { vec![channel] };
+    self
+  }
+
+  /// Scan only the given `channels`, one after another, or all channels if the list is empty.
+  pub fn channels(mut self, channels: impl IntoIterator<Item = u8>) -> ScanConfigBuilder {
+    self.channels = channels.into_iter().collect();
     self
   }
 
@@ -94,120 +228,377 @@ impl ScanConfigBuilder {
   }
 
   pub fn scan_type(mut self, scan_type: ScanType) -> ScanConfigBuilder {
-    #[cfg(debug)]
-    if let ScanType::Active { min, max } = scan_type {
-      if max != Duration::default() {
-        assert!(min <= max);
-      }
-    }
     self.scan_type = scan_type;
     self
   }
 
-  pub fn build(self) -> ScanConfig {
-    let Self { ssid, bssid, channel, show_hidden, scan_type } = self;
-    ScanConfig { ssid, bssid, channel, show_hidden, scan_type }
+  /// Return to the home channel for `dwell` between scanning two channels.
+  ///
+  /// This keeps an existing station connection or access point responsive while scanning.
+  /// Channels are then scanned one after another, even if no channel list is given.
+  pub fn home_channel_dwell(mut self, dwell: impl Into<Option<Duration>>) -> ScanConfigBuilder {
+    self.home_channel_dwell = dwell.into();
+    self
+  }
+
+  /// Use a random locally administered MAC address for the duration of the scan.
+  ///
+  /// The MAC address can only be changed while WiFi is stopped, so this fails if an access
+  /// point or station is running. The station MAC address is restored once the scan is done,
+  /// unless WiFi was started by someone else in the meantime.
+  pub fn randomize_mac(mut self, randomize_mac: bool) -> ScanConfigBuilder {
+    self.randomize_mac = randomize_mac;
+    self
+  }
+
+  /// Accept the cached results of a previous scan with the same configuration
+  /// if they are not older than `max_age`.
+  pub fn max_age(mut self, max_age: impl Into<Option<Duration>>) -> ScanConfigBuilder {
+    self.max_age = max_age.into();
+    self
+  }
+
+  pub fn build(self) -> Result<ScanConfig, ScanConfigError> {
+    if let Some(&channel) = self.channels.iter().find(|&&channel| channel == 0 || channel > MAX_CHANNEL) {
+      return Err(ScanConfigError::InvalidChannel(channel))
+    }
+
+    let durations = match self.scan_type {
+      ScanType::Active { min, max } => {
+        if max != Duration::default() && min > max {
+          return Err(ScanConfigError::InvalidActiveScanTime { min, max })
+        }
+
+        vec![min, max]
+      },
+      ScanType::Passive { max } => vec![max],
+    };
+
+    if let Some(&duration) = durations.iter().find(|duration| duration.as_millis() > u32::max_value() as u128) {
+      return Err(ScanConfigError::DurationTooLong(duration))
+    }
+
+    let Self { ssid, bssid, channels, show_hidden, scan_type, home_channel_dwell, randomize_mac, max_age } = self;
+    Ok(ScanConfig { ssid, bssid, channels, show_hidden, scan_type, home_channel_dwell, randomize_mac, max_age })
   }
 }
 
-/// An access point record returned by a [`ScanFuture`](struct.ScanFuture.html).
-#[derive(Debug, Clone)]
-pub struct ApRecord {
-  ssid: Ssid,
-  bssid: MacAddr6,
+#[derive(Debug, Default)]
+struct ScanBroadcastState {
+  done: bool,
+  result: Option<Result<ScanResults, WifiError>>,
+  wakers: Vec<Waker>,
 }
 
-impl ApRecord {
-  pub fn ssid(&self) -> &Ssid {
-    &self.ssid
+/// A running scan, whose result is shared with all callers waiting for it.
+#[derive(Debug)]
+struct ScanBroadcast {
+  config: ScanConfig,
+  state: Mutex<ScanBroadcastState>,
+}
+
+impl ScanBroadcast {
+  /// Wake all waiting callers with the result, or `None` if the scan was aborted.
+  fn complete(&self, result: Option<Result<ScanResults, WifiError>>) {
+    let mut state = self.state.lock().unwrap();
+    state.done = true;
+    state.result = result;
+
+    for waker in state.wakers.drain(..) {
+      waker.wake();
+    }
   }
 
-  pub fn bssid(&self) -> &MacAddr6 {
-    &self.bssid
+  fn poll_result(&self, cx: &mut Context) -> Poll<Option<Result<ScanResults, WifiError>>> {
+    let mut state = self.state.lock().unwrap();
+
+    if state.done {
+      return Poll::Ready(state.result.clone())
+    }
+
+    if !state.wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+      state.wakers.push(cx.waker().clone());
+    }
+
+    Poll::Pending
   }
 }
 
+/// The currently running scan and the results of the last successful scan.
+#[derive(Debug, Default)]
+struct ScanQueue {
+  running: Option<Arc<ScanBroadcast>>,
+  cached: Option<(ScanConfig, Instant, ScanResults)>,
+}
+
+impl ScanQueue {
+  fn cached(&self, config: &ScanConfig) -> Option<ScanResults> {
+    let max_age = config.max_age?;
+
+    match &self.cached {
+      Some((cached_config, instant, results)) if cached_config.same_scan(config) && instant.elapsed() <= max_age => {
+        Some(results.clone())
+      },
+      _ => None,
+    }
+  }
+}
+
+fn scan_queue() -> &'static Mutex<ScanQueue> {
+  static INIT: Once = Once::new();
+  static mut SCAN_QUEUE: Option<Mutex<ScanQueue>> = None;
+
+  INIT.call_once(|| unsafe { SCAN_QUEUE = Some(Mutex::default()) });
+  unsafe { SCAN_QUEUE.as_ref().unwrap() }
+}
+
+/// Remove a finished scan from the queue, cache its results and wake all waiting callers.
+fn publish(broadcast: &Arc<ScanBroadcast>, result: Option<Result<ScanResults, WifiError>>) {
+  {
+    let mut queue = scan_queue().lock().unwrap();
+
+    if queue.running.as_ref().map_or(false, |running| Arc::ptr_eq(running, broadcast)) {
+      queue.running = None;
+    }
+
+    if let Some(Ok(results)) = &result {
+      queue.cached = Some((broadcast.config.clone(), Instant::now(), results.clone()));
+    }
+  }
+
+  broadcast.complete(result);
+}
+
 #[derive(Debug)]
 enum ScanFutureState {
-  Starting(*const Waker),
+  /// Waiting for another scan to finish, sharing its result if `join` is set.
+  Queued { config: ScanConfig, shared: Option<Arc<ScanFutureShared>>, broadcast: Arc<ScanBroadcast>, join: bool },
+  Cached(ScanResults),
+  Scanning,
+  /// Waiting on the home channel before scanning the next channel.
+  #[cfg(target_device = "esp32")]
+  Dwelling(Delay),
   Failed(WifiError),
-  Done,
+  Finished,
+}
+
+/// State shared between a [`ScanFuture`](struct.ScanFuture.html) and its event handler.
+#[derive(Debug, Default)]
+pub(super) struct ScanFutureShared {
+  done: AtomicBool,
+  waker: WakerSlot,
+}
+
+impl ScanFutureShared {
+  /// Register a handler marking scans as done, which can be reused for consecutive scans.
+  #[cfg(target_device = "esp32")]
+  pub fn register_handler(self: &Arc<Self>) -> Result<EventHandler, EspError> {
+    let shared = Arc::clone(self);
+
+    EventHandler::register(unsafe { WIFI_EVENT }, wifi_event_t::WIFI_EVENT_SCAN_DONE as _, move |_, _, _| {
+      shared.done.store(true, SeqCst);
+      shared.waker.wake();
+    })
+  }
 }
 
 /// A future representing a scan of nearby WiFi networks.
+///
+/// Only one scan runs at a time. Scans requested while another scan with the same
+/// configuration is running share its results, other scans are started once it is done.
 #[must_use = "futures do nothing unless polled"]
 #[derive(Debug)]
 pub struct ScanFuture {
-  state: Pin<Box<ScanFutureState>>,
+  state: ScanFutureState,
+  shared: Arc<ScanFutureShared>,
+  #[cfg(target_device = "esp32")]
+  handler: Option<EventHandler>,
+  original_mac: Option<MacAddr6>,
+  broadcast: Option<Arc<ScanBroadcast>>,
+  config: ScanConfig,
+  channels: VecDeque<u8>,
+  aps: Vec<ApRecord>,
 }
 
 impl ScanFuture {
+  fn with_state(state: ScanFutureState) -> Self {
+    Self {
+      state,
+      shared: Arc::default(),
+      #[cfg(target_device = "esp32")]
+      handler: None,
+      original_mac: None,
+      broadcast: None,
+      config: ScanConfig::default(),
+      channels: VecDeque::new(),
+      aps: Vec::new(),
+    }
+  }
+
+  fn failed(err: WifiError, original_mac: Option<MacAddr6>) -> Self {
+    let mut future = Self::with_state(ScanFutureState::Failed(err));
+    future.original_mac = original_mac;
+    future
+  }
+
   #[inline]
   pub(crate) fn new(config: &ScanConfig) -> Self {
-    let mut state = Box::pin(ScanFutureState::Starting(ptr::null()));
+    Self::start(config, None)
+  }
 
-    enter_sta_mode();
+  /// Start a scan relying on a handler already registered using `shared.register_handler()`.
+  #[cfg(target_device = "esp32")]
+  pub(super) fn with_shared(config: &ScanConfig, shared: &Arc<ScanFutureShared>) -> Self {
+    Self::start(config, Some(Arc::clone(shared)))
+  }
 
-    if let Err(err) = esp_ok!(esp_wifi_start()) {
-      return Self { state: Box::pin(ScanFutureState::Failed(err.into())) };
+  /// Use cached results, wait for a running scan or start a new scan.
+  fn start(config: &ScanConfig, shared: Option<Arc<ScanFutureShared>>) -> Self {
+    let mut queue = scan_queue().lock().unwrap();
+
+    if let Some(results) = queue.cached(config) {
+      return Self::with_state(ScanFutureState::Cached(results))
     }
 
-    let duration_as_millis_rounded = |dur: Duration| {
-      let nanos = dur.as_nanos();
+    if let Some(running) = &queue.running {
+      let join = running.config.same_scan(config);
+      let broadcast = Arc::clone(running);
+      return Self::with_state(ScanFutureState::Queued { config: config.clone(), shared, broadcast, join })
+    }
 
-      if nanos == 0 {
-        0
-      } else {
-        cmp::min(u32::max_value() as u128, cmp::max(1_000_000, nanos) / 1_000_000) as u32
+    let broadcast = Arc::new(ScanBroadcast { config: config.clone(), state: Mutex::default() });
+    queue.running = Some(Arc::clone(&broadcast));
+    drop(queue);
+
+    let mut future = Self::scan(config, shared);
+
+    match &future.state {
+      ScanFutureState::Failed(err) => publish(&broadcast, Some(Err(err.clone()))),
+      _ => future.broadcast = Some(broadcast),
+    }
+
+    future
+  }
+
+  fn scan(config: &ScanConfig, shared: Option<Arc<ScanFutureShared>>) -> Self {
+    let started = is_started();
+    enter_sta_mode();
+
+    let original_mac = if config.randomize_mac {
+      if started {
+        return Self::failed(MacAddrError::Started(Interface::Sta).into(), None)
       }
-    };
 
-    let (scan_type, scan_time) = match config.scan_type {
-      ScanType::Active { min, max } => (
-        wifi_scan_type_t::WIFI_SCAN_TYPE_ACTIVE,
-        wifi_scan_time_t {
-          active: wifi_active_scan_time_t {
-            min: duration_as_millis_rounded(min),
-            max: duration_as_millis_rounded(max),
-          },
-          #[cfg(target_device = "esp32")]
-          passive: 0,
-        },
-      ),
-      ScanType::Passive { max } => (
-        wifi_scan_type_t::WIFI_SCAN_TYPE_PASSIVE,
-        wifi_scan_time_t {
-          #[cfg(target_device = "esp32")]
-          active: wifi_active_scan_time_t { min: 0, max: 0 },
-          passive: duration_as_millis_rounded(max),
-        },
-      )
+      match randomize_sta_mac() {
+        Ok(original_mac) => Some(original_mac),
+        Err(err) => return Self::failed(err, None),
+      }
+    } else {
+      None
     };
 
-    let config = wifi_scan_config_t {
-      ssid: config.ssid.as_ref().map_or_else(ptr::null_mut, |ssid| ssid.ssid.as_ptr() as *mut _),
-      bssid: config.bssid.as_ref().map_or_else(ptr::null_mut, |bssid| bssid as *const _ as *mut _),
-      channel: config.channel,
-      show_hidden: config.show_hidden,
-      scan_type,
-      scan_time,
+    if let Err(err) = esp_ok!(esp_wifi_start()) {
+      return Self::failed(err.into(), original_mac);
+    }
+
+    let channels = match config.scan_channels() {
+      Ok(channels) => channels,
+      Err(err) => return Self::failed(err.into(), original_mac),
     };
 
-    if let Err(err) = register_scan_done_handler((&mut *state) as *mut _) {
-      return Self { state: Box::pin(ScanFutureState::Failed(err.into())) };
+    let owns_handler = shared.is_none();
+    let shared = shared.unwrap_or_default();
+
+    #[cfg(target_device = "esp32")]
+    let handler = if owns_handler {
+      match shared.register_handler() {
+        Ok(handler) => Some(handler),
+        Err(err) => return Self::failed(err.into(), original_mac),
+      }
+    } else {
+      None
     };
 
-    if let Err(err) = esp_ok!(esp_wifi_scan_start(&config, false)) {
-      let _ = unregister_scan_done_handler();
-      return Self { state: Box::pin(ScanFutureState::Failed(err.into())) };
+    #[cfg(not(target_device = "esp32"))]
+    let _ = owns_handler;
+
+    let mut future = Self {
+      state: ScanFutureState::Scanning,
+      shared,
+      #[cfg(target_device = "esp32")]
+      handler,
+      original_mac,
+      broadcast: None,
+      config: config.clone(),
+      channels,
+      aps: Vec::new(),
     };
 
-    Self { state }
+    if let Err(err) = future.scan_next_channel() {
+      future.state = ScanFutureState::Failed(err.into());
+    }
+
+    future
   }
+
+  /// Start scanning the next channel, or all channels if there is no channel list.
+  fn scan_next_channel(&mut self) -> Result<(), EspError> {
+    let channel = self.channels.pop_front().unwrap_or(0);
+    self.shared.done.store(false, SeqCst);
+    esp_ok!(esp_wifi_scan_start(&self.config.to_native(channel), false))
+  }
+
+  /// Clean up and share the result with all waiting callers.
+  #[cfg(target_device = "esp32")]
+  fn complete(&mut self, result: Result<ScanResults, WifiError>) -> Poll<Result<ScanResults, WifiError>> {
+    self.finish();
+
+    if let Some(broadcast) = self.broadcast.take() {
+      publish(&broadcast, Some(result.clone()));
+    }
+
+    Poll::Ready(result)
+  }
+
+  /// Unregister the event handler, leave station mode and restore the MAC address.
+  fn finish(&mut self) {
+    #[cfg(target_device = "esp32")]
+    {
+      self.handler = None;
+    }
+
+    leave_sta_mode();
+    self.restore_mac();
+  }
+
+  /// Clean up after an aborted scan, letting waiting callers start their own scan.
+  fn abandon(&mut self) {
+    self.finish();
+
+    if let Some(broadcast) = self.broadcast.take() {
+      publish(&broadcast, None);
+    }
+  }
+
+  /// Restore the original MAC address if WiFi was stopped by leaving station mode.
+  fn restore_mac(&mut self) {
+    if let Some(original_mac) = self.original_mac.take() {
+      if !is_started() {
+        let _ = Interface::Sta.set_mac(original_mac);
+      }
+    }
+  }
+}
+
+/// Set a random MAC address on the station interface, returning the previous one.
+fn randomize_sta_mac() -> Result<MacAddr6, WifiError> {
+  let original_mac = Interface::Sta.mac()?;
+  Interface::Sta.set_mac(random_mac()).map_err(WifiError::from)?;
+  Ok(original_mac)
 }
 
 impl Future for ScanFuture {
-  type Output = Result<Vec<ApRecord>, WifiError>;
+  type Output = Result<ScanResults, WifiError>;
 
   #[cfg(target_device = "esp8266")]
   fn poll(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
@@ -216,92 +607,107 @@ impl Future for ScanFuture {
 
   #[cfg(target_device = "esp32")]
   fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
-    match &mut *self.state {
-      ScanFutureState::Starting(ref mut waker) => {
-        *waker = cx.waker() as *const _;
-        Poll::Pending
+    let this = &mut *self;
+
+    match mem::replace(&mut this.state, ScanFutureState::Finished) {
+      ScanFutureState::Finished => panic!("`ScanFuture` polled after completion"),
+      ScanFutureState::Cached(results) => Poll::Ready(Ok(results)),
+      ScanFutureState::Queued { config, shared, broadcast, join } => match broadcast.poll_result(cx) {
+        Poll::Pending => {
+          this.state = ScanFutureState::Queued { config, shared, broadcast, join };
+          Poll::Pending
+        },
+        Poll::Ready(Some(result)) if join => Poll::Ready(result),
+        Poll::Ready(_) => {
+          // The other scan is done or was aborted, so try again.
+          *this = Self::start(&config, shared);
+          Pin::new(this).poll(cx)
+        },
       },
-      ScanFutureState::Failed(ref mut err) => {
-        leave_sta_mode();
-        Poll::Ready(Err(mem::replace(err, unsafe { MaybeUninit::uninit().assume_init() })))
+      ScanFutureState::Failed(err) => {
+        this.finish();
+        Poll::Ready(Err(err))
       },
-      ScanFutureState::Done => {
-        let unregister = unregister_scan_done_handler();
-        let aps = get_ap_records();
-        leave_sta_mode();
+      ScanFutureState::Scanning => {
+        // Register before checking, so a concurrent scan completion is not missed.
+        this.shared.waker.register(cx.waker());
+
+        if !this.shared.done.load(SeqCst) {
+          this.state = ScanFutureState::Scanning;
+          return Poll::Pending
+        }
+
+        let aps = match get_ap_records() {
+          Ok(aps) => aps,
+          Err(err) => return this.complete(Err(err.into())),
+        };
+        this.aps.extend(aps);
+
+        if this.channels.is_empty() {
+          let aps = mem::take(&mut this.aps);
+          return this.complete(Ok(ScanResults::from(aps)))
+        }
+
+        match this.config.home_channel_dwell {
+          Some(dwell) => this.state = ScanFutureState::Dwelling(Delay::new(dwell)),
+          None => {
+            if let Err(err) = this.scan_next_channel() {
+              return this.complete(Err(err.into()))
+            }
+
+            this.state = ScanFutureState::Scanning;
+          },
+        }
 
-        unregister?;
-        let aps = aps?;
+        Pin::new(this).poll(cx)
+      },
+      ScanFutureState::Dwelling(mut delay) => match Pin::new(&mut delay).poll(cx) {
+        Poll::Pending => {
+          this.state = ScanFutureState::Dwelling(delay);
+          Poll::Pending
+        },
+        Poll::Ready(Err(err)) => this.complete(Err(err.into())),
+        Poll::Ready(Ok(())) => {
+          if let Err(err) = this.scan_next_channel() {
+            return this.complete(Err(err.into()))
+          }
+
+          this.state = ScanFutureState::Scanning;
+          Pin::new(this).poll(cx)
+        },
+      },
+    }
+  }
+}
 
-        Poll::Ready(Ok(aps))
-      }
+impl Drop for ScanFuture {
+  /// Aborts a pending scan.
+  fn drop(&mut self) {
+    match mem::replace(&mut self.state, ScanFutureState::Finished) {
+      ScanFutureState::Finished | ScanFutureState::Cached(_) | ScanFutureState::Queued { .. } => (),
+      ScanFutureState::Failed(_) => self.finish(),
+      ScanFutureState::Scanning => {
+        #[cfg(target_device = "esp32")]
+        {
+          self.handler = None;
+        }
+
+        let _ = esp_ok!(esp_wifi_scan_stop());
+        self.abandon();
+      },
+      #[cfg(target_device = "esp32")]
+      ScanFutureState::Dwelling(_) => self.abandon(),
     }
   }
 }
 
 #[inline]
-fn get_ap_records() -> Result<Vec<ApRecord>, EspError> {
+fn get_ap_records() -> Result<ScanResults, EspError> {
   let mut ap_num = 0;
   esp_ok!(esp_wifi_scan_get_ap_num(&mut ap_num))?;
 
   let mut aps: Vec<MaybeUninit<wifi_ap_record_t>> = vec![MaybeUninit::uninit(); ap_num as usize];
   esp_ok!(esp_wifi_scan_get_ap_records(&mut ap_num as _, aps.as_mut_ptr() as *mut wifi_ap_record_t))?;
 
-  Ok(aps.into_iter().map(|ap| {
-    let ap = unsafe { ap.assume_init() };
-
-    let ssid_len = memchr::memchr(0, &ap.ssid).unwrap_or(ap.ssid.len());
-    let ssid = unsafe { Ssid::from_bytes_unchecked(&ap.ssid[..ssid_len]) };
-
-    let bssid = MacAddr6::from(ap.bssid);
-
-    ApRecord { ssid, bssid }
-  }).collect())
-}
-
-
-#[cfg(target_device = "esp8266")]
-fn register_scan_done_handler(b: *mut ScanFutureState) -> Result<(), EspError> {
-  Ok(())
-}
-
-
-#[cfg(target_device = "esp8266")]
-#[inline]
-fn unregister_scan_done_handler() -> Result<(), EspError> {
-  Ok(())
-}
-
-#[cfg(target_device = "esp32")]
-use esp_idf_bindgen::{esp_event_handler_register, esp_event_handler_unregister, wifi_event_t, WIFI_EVENT};
-
-#[cfg(target_device = "esp32")]
-#[inline]
-fn register_scan_done_handler(b: *mut ScanFutureState) -> Result<(), EspError> {
-  esp_ok!(esp_event_handler_register(
-    WIFI_EVENT, wifi_event_t::WIFI_EVENT_SCAN_DONE as _, Some(wifi_scan_done_handler), b as *mut _,
-  ))
-}
-
-#[cfg(target_device = "esp32")]
-#[inline]
-fn unregister_scan_done_handler() -> Result<(), EspError> {
-  esp_ok!(esp_event_handler_unregister(
-    WIFI_EVENT, wifi_event_t::WIFI_EVENT_SCAN_DONE as _, Some(wifi_scan_done_handler),
-  ))
-}
-
-#[cfg(target_device = "esp32")]
-extern "C" fn wifi_scan_done_handler(
-  event_handler_arg: *mut libc::c_void,
-  _event_base: esp_idf_bindgen::esp_event_base_t,
-  _event_id: i32,
-  _event_data: *mut libc::c_void,
-) {
-  let state =  unsafe { &mut *(event_handler_arg as *mut ScanFutureState) };
-  if let ScanFutureState::Starting(waker) = mem::replace(state, ScanFutureState::Done) {
-    if let Some(waker) = unsafe { waker.as_ref() } {
-      waker.wake_by_ref();
-    }
-  }
+  Ok(aps.into_iter().map(|ap| ApRecord::from(unsafe { &ap.assume_init() })).collect())
 }
@@ -0,0 +1,297 @@
+use core::cmp::Reverse;
+use core::iter::FromIterator;
+use core::ops::Deref;
+use core::slice;
+use std::collections::{BTreeMap, BTreeSet};
+use std::vec;
+
+use macaddr::MacAddr6;
+
+use super::{ApRecord, AuthMode, Ssid};
+
+/// Relative strength of an authentication mode, used for sorting by security.
+fn security_rank(auth_mode: AuthMode) -> u8 {
+  match auth_mode {
+    AuthMode::Open | AuthMode::Max => 0,
+    AuthMode::Wep => 1,
+    AuthMode::WpaPsk => 2,
+    AuthMode::WpaWpa2Psk => 3,
+    AuthMode::Wpa2Psk => 4,
+    AuthMode::Wpa2Enterprise => 5,
+    #[cfg(target_device = "esp32")]
+    AuthMode::Wpa2Wpa3Psk => 6,
+    #[cfg(target_device = "esp32")]
+    AuthMode::Wpa3Psk => 7,
+  }
+}
+
+/// A collection of access point records returned by a [`ScanFuture`](struct.ScanFuture.html).
+#[derive(Debug, Clone, Default)]
+pub struct ScanResults {
+  aps: Vec<ApRecord>,
+}
+
+impl ScanResults {
+  pub fn into_vec(self) -> Vec<ApRecord> {
+    self.aps
+  }
+
+  /// Group access points by SSID, strongest first within each group.
+  pub fn group_by_ssid(&self) -> BTreeMap<&Ssid, Vec<&ApRecord>> {
+    let mut groups = BTreeMap::<_, Vec<_>>::new();
+
+    for ap in &self.aps {
+      groups.entry(ap.ssid()).or_default().push(ap);
+    }
+
+    for group in groups.values_mut() {
+      group.sort_by_key(|ap| Reverse(ap.rssi()));
+    }
+
+    groups
+  }
+
+  /// Keep only the strongest access point of each SSID, preserving the order of the remaining records.
+  pub fn strongest_per_ssid(mut self) -> Self {
+    let mut strongest = BTreeMap::<Ssid, (usize, i8)>::new();
+
+    for (i, ap) in self.aps.iter().enumerate() {
+      let entry = strongest.entry(ap.ssid().clone()).or_insert((i, ap.rssi()));
+      if ap.rssi() > entry.1 {
+        *entry = (i, ap.rssi());
+      }
+    }
+
+    let keep = strongest.values().map(|&(i, _)| i).collect::<BTreeSet<_>>();
+    let mut i = 0;
+    self.aps.retain(|_| {
+      i += 1;
+      keep.contains(&(i - 1))
+    });
+    self
+  }
+
+  /// Sort by signal strength, strongest first.
+  pub fn sort_by_rssi(&mut self) {
+    self.aps.sort_by_key(|ap| Reverse(ap.rssi()));
+  }
+
+  /// Sort by authentication mode, most secure first, then by signal strength.
+  pub fn sort_by_security(&mut self) {
+    self.aps.sort_by_key(|ap| (Reverse(security_rank(ap.auth_mode())), Reverse(ap.rssi())));
+  }
+
+  /// Sort by SSID, then by signal strength.
+  pub fn sort_by_ssid(&mut self) {
+    self.aps.sort_by(|a, b| a.ssid().cmp(b.ssid()).then_with(|| b.rssi().cmp(&a.rssi())));
+  }
+
+  /// Remove access points with a hidden SSID.
+  pub fn without_hidden(mut self) -> Self {
+    self.aps.retain(|ap| !ap.ssid().is_empty());
+    self
+  }
+
+  /// Remove access points without authentication.
+  pub fn without_open(mut self) -> Self {
+    self.aps.retain(|ap| ap.auth_mode() != AuthMode::Open);
+    self
+  }
+
+  /// Keep only the access points for which `f` returns `true`.
+  pub fn retain(&mut self, f: impl FnMut(&ApRecord) -> bool) {
+    self.aps.retain(f)
+  }
+
+  /// Compare these results with `previous` results by BSSID.
+  pub fn diff(&self, previous: &ScanResults) -> ScanDiff {
+    let bssids = |aps: &[ApRecord]| aps.iter().map(|ap| *ap.bssid()).collect::<BTreeSet<MacAddr6>>();
+    let (current_bssids, previous_bssids) = (bssids(&self.aps), bssids(&previous.aps));
+
+    ScanDiff {
+      appeared: self.aps.iter().filter(|ap| !previous_bssids.contains(ap.bssid())).cloned().collect(),
+      disappeared: previous.aps.iter().filter(|ap| !current_bssids.contains(ap.bssid())).cloned().collect(),
+    }
+  }
+}
+
+impl Deref for ScanResults {
+  type Target = [ApRecord];
+
+  fn deref(&self) -> &Self::Target {
+    &self.aps
+  }
+}
+
+impl From<Vec<ApRecord>> for ScanResults {
+  fn from(aps: Vec<ApRecord>) -> Self {
+    Self { aps }
+  }
+}
+
+impl FromIterator<ApRecord> for ScanResults {
+  fn from_iter<I: IntoIterator<Item = ApRecord>>(iter: I) -> Self {
+    Self { aps: iter.into_iter().collect() }
+  }
+}
+
+impl IntoIterator for ScanResults {
+  type Item = ApRecord;
+  type IntoIter = vec::IntoIter<ApRecord>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.aps.into_iter()
+  }
+}
+
+impl<'a> IntoIterator for &'a ScanResults {
+  type Item = &'a ApRecord;
+  type IntoIter = slice::Iter<'a, ApRecord>;
+
+  fn into_iter(self) -> Self::IntoIter {
+    self.aps.iter()
+  }
+}
+
+/// Differences between two [`ScanResults`](struct.ScanResults.html), see
+/// [`ScanResults::diff`](struct.ScanResults.html#method.diff).
+#[derive(Debug, Clone, Default)]
+pub struct ScanDiff {
+  appeared: Vec<ApRecord>,
+  disappeared: Vec<ApRecord>,
+}
+
+impl ScanDiff {
+  /// Access points which are only in the current results.
+  pub fn appeared(&self) -> &[ApRecord] {
+    &self.appeared
+  }
+
+  /// Access points which are only in the previous results.
+  pub fn disappeared(&self) -> &[ApRecord] {
+    &self.disappeared
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.appeared.is_empty() && self.disappeared.is_empty()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  use crate::wifi::{Cipher, Country, CountryPolicy, Phy, SecondaryChannel};
+
+  fn ap(ssid: &str, last_octet: u8, rssi: i8, auth_mode: AuthMode) -> ApRecord {
+    ApRecord {
+      ssid: Ssid::from_bytes(ssid.as_bytes()).unwrap(),
+      bssid: MacAddr6::new(0x24, 0x0a, 0xc4, 0, 0, last_octet),
+      channel: 1,
+      secondary_channel: SecondaryChannel::None,
+      rssi,
+      auth_mode,
+      pairwise_cipher: Cipher::Ccmp,
+      group_cipher: Cipher::Ccmp,
+      phy: Phy::B | Phy::G | Phy::N,
+      wps: false,
+      country: Country {
+        code: *b"DE\0",
+        first_channel: 1,
+        channel_count: 13,
+        max_tx_power: 20,
+        policy: CountryPolicy::Auto,
+      },
+    }
+  }
+
+  fn ssids(results: &ScanResults) -> Vec<&str> {
+    results.iter().map(|ap| ap.ssid().as_str()).collect()
+  }
+
+  fn last_octets(aps: &[ApRecord]) -> Vec<u8> {
+    aps.iter().map(|ap| ap.bssid().as_bytes()[5]).collect()
+  }
+
+  #[test]
+  fn strongest_per_ssid_keeps_strongest_bssid_in_order() {
+    let results = ScanResults::from(vec![
+      ap("home", 1, -70, AuthMode::Wpa2Psk),
+      ap("cafe", 2, -60, AuthMode::Open),
+      ap("home", 3, -50, AuthMode::Wpa2Psk),
+      ap("cafe", 4, -80, AuthMode::Open),
+    ]);
+
+    let strongest = results.strongest_per_ssid();
+
+    assert_eq!(ssids(&strongest), ["cafe", "home"]);
+    assert_eq!(last_octets(&strongest), [2, 3]);
+  }
+
+  #[test]
+  fn group_by_ssid_sorts_groups_by_rssi() {
+    let results = ScanResults::from(vec![
+      ap("office", 1, -75, AuthMode::Wpa2Enterprise),
+      ap("guest", 2, -65, AuthMode::Open),
+      ap("office", 3, -45, AuthMode::Wpa2Enterprise),
+    ]);
+
+    let groups = results.group_by_ssid();
+
+    assert_eq!(groups.keys().map(|ssid| ssid.as_str()).collect::<Vec<_>>(), ["guest", "office"]);
+
+    let office = &groups[&Ssid::from_bytes(b"office").unwrap()];
+    assert_eq!(office.iter().map(|ap| ap.rssi()).collect::<Vec<_>>(), [-45, -75]);
+
+    let guest = &groups[&Ssid::from_bytes(b"guest").unwrap()];
+    assert_eq!(guest.len(), 1);
+  }
+
+  #[test]
+  fn sort_by_security_then_rssi() {
+    let mut results = ScanResults::from(vec![
+      ap("open", 1, -40, AuthMode::Open),
+      ap("wpa2-weak", 2, -80, AuthMode::Wpa2Psk),
+      ap("wep", 3, -50, AuthMode::Wep),
+      ap("wpa2-strong", 4, -55, AuthMode::Wpa2Psk),
+    ]);
+
+    results.sort_by_security();
+
+    assert_eq!(ssids(&results), ["wpa2-strong", "wpa2-weak", "wep", "open"]);
+  }
+
+  #[test]
+  fn without_hidden_and_without_open() {
+    let results = ScanResults::from(vec![
+      ap("", 1, -60, AuthMode::Wpa2Psk),
+      ap("library", 2, -70, AuthMode::Open),
+      ap("home", 3, -50, AuthMode::WpaWpa2Psk),
+    ]);
+
+    assert_eq!(ssids(&results.clone().without_hidden()), ["library", "home"]);
+    assert_eq!(ssids(&results.clone().without_open()), ["", "home"]);
+    assert_eq!(ssids(&results.without_hidden().without_open()), ["home"]);
+  }
+
+  #[test]
+  fn diff_by_bssid() {
+    let previous = ScanResults::from(vec![
+      ap("home", 1, -60, AuthMode::Wpa2Psk),
+      ap("neighbour", 2, -85, AuthMode::Wpa2Psk),
+    ]);
+
+    let current = ScanResults::from(vec![
+      ap("home", 1, -55, AuthMode::Wpa2Psk),
+      ap("hotspot", 3, -65, AuthMode::Wpa2Psk),
+    ]);
+
+    let diff = current.diff(&previous);
+
+    assert!(!diff.is_empty());
+    assert_eq!(last_octets(diff.appeared()), [3]);
+    assert_eq!(last_octets(diff.disappeared()), [2]);
+
+    assert!(current.diff(&current).is_empty());
+  }
+}
@@ -0,0 +1,121 @@
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+use core::time::Duration;
+use std::sync::{Arc, Mutex, Once};
+
+use futures_core::Stream;
+
+use crate::EspError;
+use crate::event::EventHandler;
+use crate::timer::Delay;
+use super::{is_connecting, ScanConfig, ScanFuture, ScanResults, WifiError};
+use super::scan::ScanFutureShared;
+
+/// Wakers of scan streams which are currently scanning.
+fn scanning_wakers() -> &'static Mutex<Vec<Waker>> {
+  static INIT: Once = Once::new();
+  static mut SCANNING_WAKERS: Option<Mutex<Vec<Waker>>> = None;
+
+  INIT.call_once(|| unsafe { SCANNING_WAKERS = Some(Mutex::default()) });
+  unsafe { SCANNING_WAKERS.as_ref().unwrap() }
+}
+
+/// Wake all scan streams which are currently scanning, so they can abort their scan
+/// when a [`ConnectFuture`](struct.ConnectFuture.html) starts connecting.
+pub(super) fn wake_scan_streams() {
+  let wakers = { scanning_wakers().lock().unwrap().drain(..).collect::<Vec<_>>() };
+
+  for waker in wakers {
+    waker.wake();
+  }
+}
+
+#[derive(Debug)]
+enum ScanStreamState {
+  Idle,
+  Waiting(Delay),
+  Scanning(ScanFuture),
+}
+
+/// A stream of periodic scans of nearby WiFi networks.
+///
+/// A new scan is started `interval` after the previous one finished. While a
+/// [`ConnectFuture`](struct.ConnectFuture.html) is connecting, no new scans are started
+/// and a scan in progress is aborted.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct ScanStream {
+  config: ScanConfig,
+  interval: Duration,
+  shared: Arc<ScanFutureShared>,
+  _handler: EventHandler,
+  state: ScanStreamState,
+}
+
+impl ScanStream {
+  pub(crate) fn new(config: &ScanConfig, interval: Duration) -> Result<Self, EspError> {
+    let shared = Arc::new(ScanFutureShared::default());
+    let handler = shared.register_handler()?;
+
+    Ok(Self {
+      config: config.clone(),
+      interval,
+      shared,
+      _handler: handler,
+      state: ScanStreamState::Idle,
+    })
+  }
+}
+
+impl Stream for ScanStream {
+  type Item = Result<ScanResults, WifiError>;
+
+  fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    let this = &mut *self;
+
+    loop {
+      match &mut this.state {
+        ScanStreamState::Idle => {
+          this.state = if is_connecting() {
+            ScanStreamState::Waiting(Delay::new(this.interval))
+          } else {
+            ScanStreamState::Scanning(ScanFuture::with_shared(&this.config, &this.shared))
+          };
+        },
+        ScanStreamState::Waiting(delay) => match Pin::new(delay).poll(cx) {
+          Poll::Pending => return Poll::Pending,
+          Poll::Ready(Ok(())) => this.state = ScanStreamState::Idle,
+          Poll::Ready(Err(err)) => {
+            this.state = ScanStreamState::Idle;
+            return Poll::Ready(Some(Err(err.into())))
+          },
+        },
+        ScanStreamState::Scanning(_) if is_connecting() => {
+          this.state = ScanStreamState::Waiting(Delay::new(this.interval));
+        },
+        ScanStreamState::Scanning(scan) => match Pin::new(scan).poll(cx) {
+          Poll::Pending => {
+            {
+              let mut wakers = scanning_wakers().lock().unwrap();
+              if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                wakers.push(cx.waker().clone());
+              }
+            }
+
+            // Check again, so a connection started before registering is not missed.
+            if is_connecting() {
+              continue
+            }
+
+            return Poll::Pending
+          },
+          Poll::Ready(result) => {
+            this.state = ScanStreamState::Waiting(Delay::new(this.interval));
+            return Poll::Ready(Some(result))
+          },
+        },
+      }
+    }
+  }
+}
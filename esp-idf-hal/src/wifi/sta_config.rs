@@ -1,4 +1,5 @@
 use core::fmt;
+use core::time::Duration;
 
 use esp_idf_bindgen::{
   wifi_config_t,
@@ -7,9 +8,44 @@ use esp_idf_bindgen::{
   wifi_sort_method_t,
   wifi_scan_threshold_t,
 };
+use macaddr::MacAddr6;
 
 use super::{AuthMode, Ssid, Password};
 
+const MAX_CHANNEL: u8 = 14;
+
+/// Error returned by [`StaConfigBuilder::build`](struct.StaConfigBuilder.html#method.build).
+#[derive(Debug, Clone, Copy)]
+pub enum StaConfigError {
+  /// No SSID was set.
+  MissingSsid,
+  /// The password length is neither valid for WEP nor for WPA, or a 64 character
+  /// password is not a hexadecimal PSK.
+  InvalidPassword(usize),
+  /// The threshold requires an authenticated network, but no password was set.
+  MissingPassword,
+  /// The threshold RSSI is positive or the threshold authentication mode is invalid.
+  InvalidThreshold(ScanThreshold),
+  /// The channel is not between `1` and `14`.
+  InvalidChannel(u8),
+  /// Protected management frames are not supported on this target.
+  UnsupportedPmf,
+}
+
+impl fmt::Display for StaConfigError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::MissingSsid => "missing SSID".fmt(f),
+      Self::InvalidPassword(64) => "invalid password, a 64 character password must be a hexadecimal PSK".fmt(f),
+      Self::InvalidPassword(len) => write!(f, "invalid password length {}", len),
+      Self::MissingPassword => "threshold requires a password".fmt(f),
+      Self::InvalidThreshold(threshold) => write!(f, "invalid threshold {:?}", threshold),
+      Self::InvalidChannel(channel) => write!(f, "invalid channel {}", channel),
+      Self::UnsupportedPmf => "protected management frames are not supported".fmt(f),
+    }
+  }
+}
+
 /// Scan method used when connecting to an access point.
 #[derive(Debug, Clone, Copy)]
 pub enum ScanMethod {
@@ -55,12 +91,30 @@ impl From<SortMethod> for wifi_sort_method_t {
 }
 
 /// Scan threshold used when connecting to an access point.
+///
+/// Access points with a weaker signal or weaker authentication are ignored.
 #[derive(Debug, Clone, Copy)]
 pub struct ScanThreshold {
   rssi: i8,
   auth_mode: AuthMode,
 }
 
+impl ScanThreshold {
+  pub fn new(rssi: i8, auth_mode: AuthMode) -> Self {
+    Self { rssi, auth_mode }
+  }
+
+  /// The minimum signal strength in dBm.
+  pub fn rssi(&self) -> i8 {
+    self.rssi
+  }
+
+  /// The weakest accepted authentication mode.
+  pub fn auth_mode(&self) -> AuthMode {
+    self.auth_mode
+  }
+}
+
 impl Default for ScanThreshold {
   fn default() -> Self {
     Self {
@@ -79,17 +133,38 @@ impl From<ScanThreshold> for wifi_scan_threshold_t {
   }
 }
 
+/// Protected management frames (802.11w) mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pmf {
+  Disabled,
+  /// Use protected management frames if the access point supports them.
+  Capable,
+  /// Only connect to access points supporting protected management frames.
+  Required,
+}
+
+impl Default for Pmf {
+  fn default() -> Self {
+    Self::Disabled
+  }
+}
+
 /// Configuration for a station.
 #[derive(Debug, Clone)]
 pub struct StaConfig {
   ssid: Ssid,
   password: Password,
   scan_method: ScanMethod,
-  bssid: Option<[u8; 6]>,
+  bssid: Option<MacAddr6>,
   channel: Option<u8>,
   listen_interval: Option<u16>,
   sort_method: SortMethod,
   threshold: Option<ScanThreshold>,
+  pmf: Pmf,
+  hostname: Option<String>,
+  timeout: Option<Duration>,
+  association_timeout: Option<Duration>,
+  dhcp_timeout: Option<Duration>,
 }
 
 impl StaConfig {
@@ -101,6 +176,56 @@ impl StaConfig {
     &self.password
   }
 
+  pub fn scan_method(&self) -> ScanMethod {
+    self.scan_method
+  }
+
+  /// The BSSID of the access point to connect to, if set.
+  pub fn bssid(&self) -> Option<&MacAddr6> {
+    self.bssid.as_ref()
+  }
+
+  /// The channel of the access point to connect to, if set.
+  pub fn channel(&self) -> Option<u8> {
+    self.channel
+  }
+
+  pub fn listen_interval(&self) -> Option<u16> {
+    self.listen_interval
+  }
+
+  pub fn sort_method(&self) -> SortMethod {
+    self.sort_method
+  }
+
+  pub fn threshold(&self) -> Option<ScanThreshold> {
+    self.threshold
+  }
+
+  pub fn pmf(&self) -> Pmf {
+    self.pmf
+  }
+
+  /// The hostname of the station interface, if set explicitly.
+  pub fn hostname(&self) -> Option<&str> {
+    self.hostname.as_deref()
+  }
+
+  /// The maximum duration of the whole connection process.
+  pub fn timeout(&self) -> Option<Duration> {
+    self.timeout
+  }
+
+  /// The maximum duration until associated with the access point.
+  pub fn association_timeout(&self) -> Option<Duration> {
+    self.association_timeout
+  }
+
+  /// The maximum duration until an IP address is obtained after associating.
+  pub fn dhcp_timeout(&self) -> Option<Duration> {
+    self.dhcp_timeout
+  }
+
   pub fn builder() -> StaConfigBuilder {
     StaConfigBuilder::default()
   }
@@ -114,15 +239,19 @@ impl From<&StaConfig> for wifi_config_t {
         password: sta_config.password.password,
         scan_method: sta_config.scan_method.into(),
         bssid_set: sta_config.bssid.is_some(),
-        bssid: sta_config.bssid.unwrap_or([0, 0, 0, 0, 0, 0]),
+        bssid: sta_config.bssid.map_or([0; 6], |bssid| {
+          let mut bytes = [0; 6];
+          bytes.copy_from_slice(bssid.as_bytes());
+          bytes
+        }),
         channel: sta_config.channel.unwrap_or(0),
         listen_interval: sta_config.listen_interval.unwrap_or(0),
         sort_method: sta_config.sort_method.into(),
         threshold: sta_config.threshold.unwrap_or_default().into(),
         #[cfg(target_device = "esp32")]
         pmf_cfg: esp_idf_bindgen::wifi_pmf_config_t {
-          capable: false,
-          required: false,
+          capable: sta_config.pmf != Pmf::Disabled,
+          required: sta_config.pmf == Pmf::Required,
         },
       }
     }
@@ -134,11 +263,16 @@ pub struct StaConfigBuilder {
   ssid: Option<Ssid>,
   password: Password,
   scan_method: ScanMethod,
-  bssid: Option<[u8; 6]>,
+  bssid: Option<MacAddr6>,
   channel: Option<u8>,
   listen_interval: Option<u16>,
   sort_method: SortMethod,
   threshold: Option<ScanThreshold>,
+  pmf: Pmf,
+  hostname: Option<String>,
+  timeout: Option<Duration>,
+  association_timeout: Option<Duration>,
+  dhcp_timeout: Option<Duration>,
 }
 
 impl fmt::Debug for StaConfigBuilder {
@@ -152,6 +286,11 @@ impl fmt::Debug for StaConfigBuilder {
       .field("listen_interval", &self.listen_interval)
       .field("sort_method", &self.sort_method)
       .field("threshold", &self.threshold)
+      .field("pmf", &self.pmf)
+      .field("hostname", &self.hostname)
+      .field("timeout", &self.timeout)
+      .field("association_timeout", &self.association_timeout)
+      .field("dhcp_timeout", &self.dhcp_timeout)
       .finish()
   }
 }
@@ -167,6 +306,11 @@ impl Default for StaConfigBuilder {
       listen_interval: Default::default(),
       sort_method: Default::default(),
       threshold: Default::default(),
+      pmf: Default::default(),
+      hostname: Default::default(),
+      timeout: Default::default(),
+      association_timeout: Default::default(),
+      dhcp_timeout: Default::default(),
     }
   }
 }
@@ -182,9 +326,107 @@ impl StaConfigBuilder {
     self
   }
 
-  pub fn build(&self) -> StaConfig {
-    StaConfig {
-      ssid: self.ssid.clone().expect("missing SSID"),
+  pub fn scan_method(&mut self, scan_method: ScanMethod) -> &mut Self {
+    self.scan_method = scan_method;
+    self
+  }
+
+  /// Only connect to the access point with the given `bssid`.
+  pub fn bssid(&mut self, bssid: impl Into<Option<MacAddr6>>) -> &mut Self {
+    self.bssid = bssid.into();
+    self
+  }
+
+  /// Only scan `channel` for the access point.
+  pub fn channel(&mut self, channel: impl Into<Option<u8>>) -> &mut Self {
+    self.channel = channel.into();
+    self
+  }
+
+  /// Set the listen interval for power saving in beacon intervals.
+  pub fn listen_interval(&mut self, listen_interval: impl Into<Option<u16>>) -> &mut Self {
+    self.listen_interval = listen_interval.into();
+    self
+  }
+
+  pub fn sort_method(&mut self, sort_method: SortMethod) -> &mut Self {
+    self.sort_method = sort_method;
+    self
+  }
+
+  /// Ignore access points with a weaker signal or weaker authentication than `threshold`.
+  pub fn threshold(&mut self, threshold: impl Into<Option<ScanThreshold>>) -> &mut Self {
+    self.threshold = threshold.into();
+    self
+  }
+
+  pub fn pmf(&mut self, pmf: Pmf) -> &mut Self {
+    self.pmf = pmf;
+    self
+  }
+
+  /// Set the hostname of the station interface.
+  ///
+  /// Defaults to [`Interface::default_hostname`](../interface/enum.Interface.html#method.default_hostname).
+  pub fn hostname(&mut self, hostname: impl Into<String>) -> &mut Self {
+    self.hostname = Some(hostname.into());
+    self
+  }
+
+  /// Fail connecting if not connected with an IP address within `timeout`.
+  pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.timeout = Some(timeout);
+    self
+  }
+
+  /// Fail connecting if not associated with the access point within `timeout`.
+  pub fn association_timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.association_timeout = Some(timeout);
+    self
+  }
+
+  /// Fail connecting if no IP address is obtained within `timeout` after associating.
+  pub fn dhcp_timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.dhcp_timeout = Some(timeout);
+    self
+  }
+
+  pub fn build(&self) -> Result<StaConfig, StaConfigError> {
+    let ssid = self.ssid.clone().ok_or(StaConfigError::MissingSsid)?;
+
+    let password = self.password.as_str();
+    let len = password.len();
+    if len != 0 && len != 5 && len != 13 && !(8..=64).contains(&len) {
+      return Err(StaConfigError::InvalidPassword(len))
+    }
+
+    // A 64 character password is used as a raw hexadecimal PSK.
+    if len == 64 && !password.bytes().all(|b| b.is_ascii_hexdigit()) {
+      return Err(StaConfigError::InvalidPassword(len))
+    }
+
+    if let Some(threshold) = self.threshold {
+      match threshold.auth_mode {
+        AuthMode::Max => return Err(StaConfigError::InvalidThreshold(threshold)),
+        _ if threshold.rssi > 0 => return Err(StaConfigError::InvalidThreshold(threshold)),
+        AuthMode::Open => (),
+        _ if len == 0 => return Err(StaConfigError::MissingPassword),
+        _ => (),
+      }
+    }
+
+    if let Some(channel) = self.channel {
+      if channel == 0 || channel > MAX_CHANNEL {
+        return Err(StaConfigError::InvalidChannel(channel))
+      }
+    }
+
+    if cfg!(not(target_device = "esp32")) && self.pmf != Pmf::Disabled {
+      return Err(StaConfigError::UnsupportedPmf)
+    }
+
+    Ok(StaConfig {
+      ssid,
       password: self.password.clone(),
       scan_method: self.scan_method,
       bssid: self.bssid,
@@ -192,6 +434,11 @@ impl StaConfigBuilder {
       listen_interval: self.listen_interval,
       sort_method: self.sort_method,
       threshold: self.threshold,
-    }
+      pmf: self.pmf,
+      hostname: self.hostname.clone(),
+      timeout: self.timeout,
+      association_timeout: self.association_timeout,
+      dhcp_timeout: self.dhcp_timeout,
+    })
   }
 }
@@ -0,0 +1,117 @@
+use core::mem::MaybeUninit;
+use std::net::Ipv4Addr;
+
+#[cfg(target_device = "esp32")]
+use esp_idf_bindgen::{
+  esp_netif_get_sta_list,
+  esp_netif_sta_list_t as sta_ip_list_t,
+  esp_wifi_ap_get_sta_aid,
+  esp_wifi_deauth_sta,
+};
+#[cfg(target_device = "esp8266")]
+use esp_idf_bindgen::{
+  tcpip_adapter_get_sta_list as esp_netif_get_sta_list,
+  tcpip_adapter_sta_list_t as sta_ip_list_t,
+};
+use esp_idf_bindgen::{esp_wifi_ap_get_sta_list, wifi_sta_info_t, wifi_sta_list_t};
+use macaddr::MacAddr6;
+
+use crate::EspError;
+use super::{ApConfig, ApStaConfig, Phy, Wifi};
+
+/// A station connected to the access point.
+#[derive(Debug, Clone)]
+pub struct Station {
+  mac: MacAddr6,
+  rssi: i8,
+  phy: Phy,
+  ip: Option<Ipv4Addr>,
+}
+
+impl Station {
+  pub fn mac(&self) -> &MacAddr6 {
+    &self.mac
+  }
+
+  /// The signal strength in dBm.
+  pub fn rssi(&self) -> i8 {
+    self.rssi
+  }
+
+  /// The PHY modes used by the station.
+  pub fn phy(&self) -> Phy {
+    self.phy
+  }
+
+  /// The IP address assigned by the DHCP server, if any.
+  pub fn ip(&self) -> Option<Ipv4Addr> {
+    self.ip
+  }
+}
+
+impl From<&wifi_sta_info_t> for Station {
+  fn from(sta: &wifi_sta_info_t) -> Self {
+    Self {
+      mac: MacAddr6::from(sta.mac),
+      rssi: sta.rssi,
+      phy: Phy::from_flags(sta.phy_11b() != 0, sta.phy_11g() != 0, sta.phy_11n() != 0, sta.phy_lr() != 0),
+      ip: None,
+    }
+  }
+}
+
+fn stations() -> Result<Vec<Station>, EspError> {
+  let mut sta_list = MaybeUninit::<wifi_sta_list_t>::uninit();
+  esp_ok!(esp_wifi_ap_get_sta_list(sta_list.as_mut_ptr()))?;
+  let sta_list = unsafe { sta_list.assume_init() };
+
+  let mut ip_list = MaybeUninit::<sta_ip_list_t>::uninit();
+  esp_ok!(esp_netif_get_sta_list(&sta_list, ip_list.as_mut_ptr()))?;
+  let ip_list = unsafe { ip_list.assume_init() };
+  let ip_list = &ip_list.sta[..ip_list.num as usize];
+
+  Ok(sta_list.sta[..sta_list.num as usize].iter().map(|sta| {
+    let mut station = Station::from(sta);
+
+    station.ip = ip_list.iter()
+      .find(|lease| lease.mac == sta.mac)
+      .map(|lease| Ipv4Addr::from(u32::from_be(lease.ip.addr)))
+      .filter(|ip| !ip.is_unspecified());
+
+    station
+  }).collect())
+}
+
+/// Deauthenticate the station with the given `mac`.
+#[cfg(target_device = "esp32")]
+fn deauth(mac: MacAddr6) -> Result<(), EspError> {
+  let mut aid = 0;
+  esp_ok!(esp_wifi_ap_get_sta_aid(mac.as_bytes().as_ptr(), &mut aid))?;
+  esp_ok!(esp_wifi_deauth_sta(aid))
+}
+
+impl Wifi<ApConfig> {
+  /// List the stations connected to the access point.
+  pub fn stations(&self) -> Result<Vec<Station>, EspError> {
+    stations()
+  }
+
+  /// Disconnect the station with the given `mac` from the access point.
+  #[cfg(target_device = "esp32")]
+  pub fn deauth(&mut self, mac: MacAddr6) -> Result<(), EspError> {
+    deauth(mac)
+  }
+}
+
+impl Wifi<ApStaConfig> {
+  /// List the stations connected to the access point.
+  pub fn stations(&self) -> Result<Vec<Station>, EspError> {
+    stations()
+  }
+
+  /// Disconnect the station with the given `mac` from the access point.
+  #[cfg(target_device = "esp32")]
+  pub fn deauth(&mut self, mac: MacAddr6) -> Result<(), EspError> {
+    deauth(mac)
+  }
+}
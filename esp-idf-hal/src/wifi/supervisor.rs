@@ -0,0 +1,498 @@
+use core::pin::Pin;
+use core::task::{Context, Poll};
+use core::time::Duration;
+use std::sync::{Arc, Mutex, Weak};
+use std::sync::mpsc::{self, Sender};
+use std::thread::{self, JoinHandle};
+
+use esp_idf_bindgen::{
+  esp_err_t,
+  esp_interface_t,
+  esp_random,
+  esp_wifi_connect,
+  esp_wifi_set_config,
+  ip_event_t,
+  wifi_config_t,
+  wifi_err_reason_t,
+  wifi_event_sta_disconnected_t,
+  wifi_event_t,
+  ESP_ERR_NO_MEM,
+  IP_EVENT,
+  WIFI_EVENT,
+};
+use futures_core::Stream;
+
+use crate::EspError;
+use crate::event::EventHandler;
+use crate::interface::{Interface, NetIf};
+use crate::timer::{Delay, Timer};
+use super::{enter_ap_mode, leave_ap_mode, ApConfig, ApStaConfig, StaConfig, Wifi, WifiError, WifiRunning};
+use super::event::EventQueue;
+
+/// Configuration for supervising a station connection, see
+/// [`Wifi::supervise`](struct.Wifi.html#method.supervise).
+#[derive(Debug, Clone)]
+pub struct SupervisorConfig {
+  initial_backoff: Duration,
+  max_backoff: Duration,
+  multiplier: u32,
+  jitter: f32,
+  fallback_ap: Option<(u32, ApConfig)>,
+}
+
+impl SupervisorConfig {
+  /// The delay before the first reconnection attempt.
+  pub fn initial_backoff(&self) -> Duration {
+    self.initial_backoff
+  }
+
+  /// The maximum delay between reconnection attempts.
+  pub fn max_backoff(&self) -> Duration {
+    self.max_backoff
+  }
+
+  /// The factor by which the delay grows after each failed attempt.
+  pub fn multiplier(&self) -> u32 {
+    self.multiplier
+  }
+
+  /// The fraction by which each delay is randomly varied.
+  pub fn jitter(&self) -> f32 {
+    self.jitter
+  }
+
+  /// The number of consecutive failures after which the fallback access point is started.
+  pub fn fallback_after(&self) -> Option<u32> {
+    self.fallback_ap.as_ref().map(|(after, _)| *after)
+  }
+
+  /// The configuration of the fallback access point.
+  pub fn fallback_ap(&self) -> Option<&ApConfig> {
+    self.fallback_ap.as_ref().map(|(_, config)| config)
+  }
+
+  pub fn builder() -> SupervisorConfigBuilder {
+    SupervisorConfigBuilder::default()
+  }
+
+  /// The delay before the next attempt after `failures` consecutive failures.
+  fn backoff(&self, failures: u32) -> Duration {
+    let mut backoff = self.initial_backoff;
+
+    for _ in 1..failures {
+      backoff = backoff.checked_mul(self.multiplier).unwrap_or(self.max_backoff);
+      if backoff >= self.max_backoff {
+        break
+      }
+    }
+
+    let backoff = backoff.min(self.max_backoff);
+
+    // Random factor in `[-1.0, 1.0]`.
+    let random = unsafe { esp_random() } as f32 / u32::MAX as f32 * 2.0 - 1.0;
+    backoff.mul_f32((1.0 + self.jitter * random).max(0.0))
+  }
+}
+
+/// Builder for [`SupervisorConfig`](struct.SupervisorConfig.html).
+#[derive(Debug, Clone)]
+pub struct SupervisorConfigBuilder {
+  initial_backoff: Duration,
+  max_backoff: Duration,
+  multiplier: u32,
+  jitter: f32,
+  fallback_ap: Option<(u32, ApConfig)>,
+}
+
+impl Default for SupervisorConfigBuilder {
+  fn default() -> Self {
+    Self {
+      initial_backoff: Duration::from_secs(1),
+      max_backoff: Duration::from_secs(60),
+      multiplier: 2,
+      jitter: 0.1,
+      fallback_ap: None,
+    }
+  }
+}
+
+impl SupervisorConfigBuilder {
+  pub fn initial_backoff(&mut self, initial_backoff: Duration) -> &mut Self {
+    self.initial_backoff = initial_backoff;
+    self
+  }
+
+  pub fn max_backoff(&mut self, max_backoff: Duration) -> &mut Self {
+    self.max_backoff = max_backoff;
+    self
+  }
+
+  pub fn multiplier(&mut self, multiplier: u32) -> &mut Self {
+    self.multiplier = multiplier.max(1);
+    self
+  }
+
+  /// Set the fraction by which each delay is randomly varied, clamped to `0.0..=1.0`.
+  pub fn jitter(&mut self, jitter: f32) -> &mut Self {
+    self.jitter = jitter.max(0.0).min(1.0);
+    self
+  }
+
+  /// Start an access point using `config` after `after_failures` consecutive failures.
+  ///
+  /// The access point is stopped again once the station reconnects.
+  pub fn fallback_ap(&mut self, after_failures: u32, config: ApConfig) -> &mut Self {
+    self.fallback_ap = Some((after_failures.max(1), config));
+    self
+  }
+
+  pub fn build(&self) -> SupervisorConfig {
+    SupervisorConfig {
+      initial_backoff: self.initial_backoff,
+      max_backoff: self.max_backoff.max(self.initial_backoff),
+      multiplier: self.multiplier,
+      jitter: self.jitter,
+      fallback_ap: self.fallback_ap.clone(),
+    }
+  }
+}
+
+/// State of a supervised station connection.
+#[derive(Debug, Clone)]
+pub enum SupervisorState {
+  /// Connected to the access point with an IP address.
+  Connected,
+  /// Trying to connect to the access point.
+  Connecting { attempt: u32 },
+  /// Disconnected, the next attempt is made after `retry_in`.
+  ///
+  /// The `reason` is a raw `wifi_err_reason_t` value.
+  Backoff { failures: u32, reason: u8, retry_in: Duration },
+  /// Disconnected while the fallback access point is running, the next attempt is made after `retry_in`.
+  FallbackAp { failures: u32, reason: u8, retry_in: Duration },
+  /// Starting the fallback access point, scheduling or starting the next attempt failed.
+  Error(EspError),
+}
+
+/// Work done by the supervisor thread, sent by event handlers and the retry timer.
+#[derive(Debug)]
+enum Command {
+  Disconnected { reason: u8 },
+  Retry,
+  GotIp,
+  Stop,
+}
+
+#[derive(Debug)]
+struct SupervisorInner {
+  config: SupervisorConfig,
+  state: SupervisorState,
+  failures: u32,
+  fallback_netif: Option<NetIf>,
+  subscribers: Vec<Weak<Mutex<EventQueue<SupervisorState>>>>,
+}
+
+impl SupervisorInner {
+  fn set_state(&mut self, state: SupervisorState) {
+    self.subscribers.retain(|subscriber| {
+      match subscriber.upgrade() {
+        Some(queue) => {
+          queue.lock().unwrap().push(state.clone());
+          true
+        },
+        None => false,
+      }
+    });
+
+    self.state = state;
+  }
+
+  fn disconnected(&mut self, reason: u8, timer: &Timer) {
+    // Disconnected deliberately using `esp_wifi_disconnect`.
+    if reason == wifi_err_reason_t::WIFI_REASON_ASSOC_LEAVE as u8 {
+      return
+    }
+
+    self.failures += 1;
+    let failures = self.failures;
+    let retry_in = self.config.backoff(failures);
+
+    let fallback_ap = if self.config.fallback_after() == Some(failures) && self.fallback_netif.is_none() {
+      self.start_fallback_ap()
+    } else {
+      Ok(())
+    };
+
+    match fallback_ap {
+      // Keep reporting the error until the next attempt.
+      Err(err) => self.set_state(SupervisorState::Error(err)),
+      Ok(()) if self.fallback_netif.is_some() => {
+        self.set_state(SupervisorState::FallbackAp { failures, reason, retry_in });
+      },
+      Ok(()) => self.set_state(SupervisorState::Backoff { failures, reason, retry_in }),
+    }
+
+    if let Err(err) = timer.start(retry_in) {
+      self.set_state(SupervisorState::Error(err));
+    }
+  }
+
+  fn retry(&mut self) {
+    let attempt = self.failures + 1;
+    self.set_state(SupervisorState::Connecting { attempt });
+
+    if let Err(err) = esp_ok!(esp_wifi_connect()) {
+      self.set_state(SupervisorState::Error(err));
+    }
+  }
+
+  fn connected(&mut self) {
+    self.failures = 0;
+    self.stop_fallback_ap();
+    self.set_state(SupervisorState::Connected);
+  }
+
+  fn start_fallback_ap(&mut self) -> Result<(), EspError> {
+    let config = match &self.config.fallback_ap {
+      Some((_, config)) => config,
+      None => return Ok(()),
+    };
+
+    let interface = Interface::Ap;
+    let netif = interface.netif()?;
+    if let Some(dhcp_server) = config.dhcp_server() {
+      interface.set_dhcp_server_config(dhcp_server)?;
+    }
+
+    let mut ap_config = wifi_config_t::from(config);
+    enter_ap_mode();
+    if let Err(err) = esp_ok!(esp_wifi_set_config(esp_interface_t::ESP_IF_WIFI_AP, &mut ap_config)) {
+      leave_ap_mode();
+      return Err(err)
+    }
+
+    self.fallback_netif = Some(netif);
+    Ok(())
+  }
+
+  fn stop_fallback_ap(&mut self) {
+    if self.fallback_netif.take().is_some() {
+      leave_ap_mode();
+    }
+  }
+}
+
+/// Reconnects a station on disconnection, see [`Wifi::supervise`](struct.Wifi.html#method.supervise).
+///
+/// Event handlers and the retry timer only notify a dedicated thread, which changes the WiFi mode
+/// and reconnects, so neither the event loop task nor the `esp_timer` task is blocked.
+#[derive(Debug)]
+pub(super) struct Supervisor {
+  _disconnected_handler: EventHandler,
+  _got_ip_handler: EventHandler,
+  commands: Sender<Command>,
+  thread: Option<JoinHandle<()>>,
+  inner: Arc<Mutex<SupervisorInner>>,
+}
+
+impl Supervisor {
+  pub fn new(config: SupervisorConfig) -> Result<Self, EspError> {
+    let inner = Arc::new(Mutex::new(SupervisorInner {
+      config,
+      state: SupervisorState::Connected,
+      failures: 0,
+      fallback_netif: None,
+      subscribers: Vec::new(),
+    }));
+
+    let (commands, receiver) = mpsc::channel();
+
+    let timer_commands = commands.clone();
+    let timer = Timer::new(move || {
+      let _ = timer_commands.send(Command::Retry);
+    })?;
+
+    let handler_commands = commands.clone();
+    let disconnected_handler = EventHandler::register(
+      unsafe { WIFI_EVENT }, wifi_event_t::WIFI_EVENT_STA_DISCONNECTED as _,
+      move |_, _, event_data| {
+        let event = unsafe { &*(event_data as *const wifi_event_sta_disconnected_t) };
+        let _ = handler_commands.send(Command::Disconnected { reason: event.reason });
+      },
+    )?;
+
+    let handler_commands = commands.clone();
+    let got_ip_handler = EventHandler::register(
+      unsafe { IP_EVENT }, ip_event_t::IP_EVENT_STA_GOT_IP as _,
+      move |_, _, _| {
+        let _ = handler_commands.send(Command::GotIp);
+      },
+    )?;
+
+    let thread_inner = Arc::clone(&inner);
+    let thread = thread::Builder::new()
+      .name("wifi_supervisor".into())
+      .stack_size(4096)
+      .spawn(move || {
+        for command in receiver {
+          let mut inner = thread_inner.lock().unwrap();
+
+          match command {
+            Command::Disconnected { reason } => inner.disconnected(reason, &timer),
+            Command::Retry => inner.retry(),
+            Command::GotIp => inner.connected(),
+            Command::Stop => break,
+          }
+        }
+      })
+      .map_err(|_| EspError { code: ESP_ERR_NO_MEM as esp_err_t })?;
+
+    Ok(Self {
+      _disconnected_handler: disconnected_handler,
+      _got_ip_handler: got_ip_handler,
+      commands,
+      thread: Some(thread),
+      inner,
+    })
+  }
+
+  pub fn config(&self) -> SupervisorConfig {
+    self.inner.lock().unwrap().config.clone()
+  }
+
+  pub fn state(&self) -> SupervisorState {
+    self.inner.lock().unwrap().state.clone()
+  }
+
+  pub fn states(&self, capacity: usize) -> SupervisorStates {
+    let queue = Arc::new(Mutex::new(EventQueue::new(capacity)));
+    self.inner.lock().unwrap().subscribers.push(Arc::downgrade(&queue));
+    SupervisorStates { queue }
+  }
+
+  /// Take over the fallback access point, if it is running.
+  fn take_fallback_ap(&self) -> Option<(ApConfig, NetIf)> {
+    let mut inner = self.inner.lock().unwrap();
+    let config = inner.config.fallback_ap()?.clone();
+    let netif = inner.fallback_netif.take()?;
+    Some((config, netif))
+  }
+}
+
+impl Drop for Supervisor {
+  fn drop(&mut self) {
+    if let Some(thread) = self.thread.take() {
+      let _ = self.commands.send(Command::Stop);
+      let _ = thread.join();
+    }
+
+    let mut inner = self.inner.lock().unwrap();
+    inner.stop_fallback_ap();
+
+    for queue in inner.subscribers.drain(..).filter_map(|subscriber| subscriber.upgrade()) {
+      queue.lock().unwrap().close();
+    }
+  }
+}
+
+/// A stream of [`SupervisorState`](enum.SupervisorState.html) transitions.
+///
+/// If a subscriber falls behind, the oldest buffered states are dropped.
+/// The stream ends once the supervisor is stopped.
+#[must_use = "streams do nothing unless polled"]
+#[derive(Debug)]
+pub struct SupervisorStates {
+  queue: Arc<Mutex<EventQueue<SupervisorState>>>,
+}
+
+impl Stream for SupervisorStates {
+  type Item = SupervisorState;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+    self.queue.lock().unwrap().poll_pop(cx)
+  }
+}
+
+impl Wifi {
+  /// Connect to a WiFi network and keep the connection supervised.
+  ///
+  /// Failed connection attempts are retried with the backoff configured in `supervisor`.
+  /// If a fallback access point is configured, it is started instead after the configured
+  /// number of consecutive failures. Once connected, the connection is supervised as
+  /// described in [`supervise`](struct.Wifi.html#method.supervise).
+  pub async fn connect_sta_supervised(self, config: StaConfig, supervisor: SupervisorConfig) -> Result<WifiRunning, WifiError> {
+    let mut wifi = self;
+    let mut failures = 0;
+
+    loop {
+      match wifi.connect_sta(config.clone()).await {
+        Ok(WifiRunning::Sta(mut sta)) => {
+          sta.supervise(supervisor)?;
+          return Ok(WifiRunning::Sta(sta))
+        },
+        Ok(running) => return Ok(running),
+        Err(err) => {
+          failures += 1;
+          wifi = err.wifi();
+
+          if let Some((after, ap_config)) = &supervisor.fallback_ap {
+            if failures >= *after {
+              return wifi.start_ap(ap_config.clone())
+            }
+          }
+
+          Delay::new(supervisor.backoff(failures)).await?;
+        },
+      }
+    }
+  }
+}
+
+impl Wifi<StaConfig> {
+  /// Supervise the station connection, replacing any existing supervisor.
+  ///
+  /// When the connection is lost, reconnection is attempted with exponential backoff.
+  /// Deliberately disconnecting using `esp_wifi_disconnect` is not retried.
+  /// If a fallback access point is configured, it is started alongside the station
+  /// after the configured number of consecutive failures and stopped once reconnected.
+  pub fn supervise(&mut self, config: SupervisorConfig) -> Result<(), EspError> {
+    self.supervisor = None;
+    self.supervisor = Some(Supervisor::new(config)?);
+    Ok(())
+  }
+
+  /// Stop supervising the station connection.
+  pub fn stop_supervisor(&mut self) {
+    self.supervisor = None;
+  }
+
+  /// The configuration of the supervisor, if the connection is supervised.
+  pub fn supervisor_config(&self) -> Option<SupervisorConfig> {
+    self.supervisor.as_ref().map(Supervisor::config)
+  }
+
+  /// The current state of the supervised connection.
+  pub fn supervisor_state(&self) -> Option<SupervisorState> {
+    self.supervisor.as_ref().map(Supervisor::state)
+  }
+
+  /// Subscribe to state transitions of the supervised connection, buffering up to
+  /// `capacity` transitions.
+  pub fn supervisor_states(&self, capacity: usize) -> Option<SupervisorStates> {
+    self.supervisor.as_ref().map(|supervisor| supervisor.states(capacity))
+  }
+
+  /// Stop supervising and keep the fallback access point running alongside the station.
+  ///
+  /// Returns `self` unchanged if the fallback access point is not running.
+  pub fn into_ap_sta(self) -> Result<Wifi<ApStaConfig>, Self> {
+    let (ap_config, netif) = match self.supervisor.as_ref().and_then(Supervisor::take_fallback_ap) {
+      Some(fallback_ap) => fallback_ap,
+      None => return Err(self),
+    };
+
+    let (sta_config, _, mut netifs) = self.into_parts();
+    netifs.push(netif);
+    Ok(Wifi::from_parts(ApStaConfig::new(ap_config, sta_config), Interface::Ap.ip_info(), netifs))
+  }
+}